@@ -0,0 +1,87 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Registers every sketch in `src/bin` with the `sketchbook` binary. Each
+/// becomes a module including its source, so sketches build as one binary
+/// instead of linking nannou once each.
+fn main() {
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let dir = Path::new(&root).join("src/bin");
+    // Watch the directory for added and removed sketches, and every sketch
+    // file for edits, because edits decide whether a file has a `main`.
+    println!("cargo:rerun-if-changed=src/bin");
+
+    let mut names = fs::read_dir(&dir)
+        .unwrap()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let name = name.strip_suffix(".rs")?.to_owned();
+            is_sketch(&name).then_some(name)
+        })
+        .collect::<Vec<String>>();
+    names.sort();
+
+    let mut modules = String::new();
+    let mut registry = String::from("pub const SKETCHES: &[Entry] = &[\n");
+    for name in names {
+        let path = dir.join(format!("{}.rs", name));
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = fs::read_to_string(&path).unwrap();
+        // Fresh sketches from `luna new` have no main yet.
+        if !source.contains("fn main(") {
+            continue;
+        }
+        let module = format!("sketch_{}", name.replace('-', "_"));
+        writeln!(
+            modules,
+            "#[allow(warnings, clippy::all)]\nmod {} {{\n    include!({:?});\n    pub fn registered_main() {{\n        main()\n    }}\n}}",
+            module, path
+        )
+        .unwrap();
+        writeln!(
+            registry,
+            "    Entry {{ name: {:?}, main: {}::registered_main, renders: {} }},",
+            name,
+            module,
+            source.contains("runner::run::<")
+        )
+        .unwrap();
+    }
+    registry.push_str("];\n");
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("sketches.rs");
+    fs::write(out, modules + &registry).unwrap();
+
+    revision(&root);
+}
+
+/// Bakes `git describe --always --dirty` into the crate as
+/// `RUSTYART_REVISION`, for the metadata of captures. Reruns when the
+/// checked out commit or anything in the tree changes.
+fn revision(root: &str) {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+    if let Some(dir) = git(&["rev-parse", "--git-dir"]) {
+        let dir = Path::new(root).join(dir);
+        println!("cargo:rerun-if-changed={}", dir.join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", dir.join("index").display());
+        println!("cargo:rerun-if-changed=src");
+    }
+    let revision = git(&["describe", "--always", "--dirty"]);
+    println!(
+        "cargo:rustc-env=RUSTYART_REVISION={}",
+        revision.as_deref().unwrap_or("unknown")
+    );
+}
+
+include!("src/sketch_name.rs");
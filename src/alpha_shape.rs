@@ -0,0 +1,339 @@
+use crate::gui::Ui;
+use crate::presets::Lerp;
+use crate::render::{contains, Canvas};
+use crate::voronoi::{circumcenter, signed_area};
+use delaunator::{next_halfedge, Triangulation, EMPTY};
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One connected piece of an alpha shape. The outline runs counterclockwise,
+/// holes clockwise.
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub outline: Vec<Vec2>,
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+impl Region {
+    /// The outline followed by the holes.
+    pub fn rings(&self) -> impl Iterator<Item = &Vec<Vec2>> {
+        std::iter::once(&self.outline).chain(self.holes.iter())
+    }
+
+    /// Area without the holes.
+    pub fn area(&self) -> f32 {
+        self.rings().map(|ring| signed_area(ring)).sum()
+    }
+
+    /// Every ring rounded by `iterations` rounds of Chaikin's corner cutting.
+    pub fn smoothed(&self, iterations: usize) -> Region {
+        Region {
+            outline: smooth(&self.outline, iterations),
+            holes: self
+                .holes
+                .iter()
+                .map(|hole| smooth(hole, iterations))
+                .collect(),
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut impl Canvas,
+        style: ShapeStyle,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let color = color.into_lin_srgba();
+        match style {
+            ShapeStyle::Off => (),
+            ShapeStyle::Filled => canvas.region(&self.rings().cloned().collect::<Vec<_>>(), color),
+            ShapeStyle::Outlined => {
+                for ring in self.rings() {
+                    let next = ring.iter().cycle().skip(1);
+                    for (&start, &end) in ring.iter().zip(next) {
+                        canvas.line(start, end, weight, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The alpha shape of `points`, which `triangulation` has to be made of: the
+/// union of the triangles with a circumradius of at most `alpha`. Its
+/// boundary is made of the halfedges of those triangles whose other side is
+/// outside, chained into rings. Small `alpha` breaks the cloud into pieces
+/// and opens holes, infinite `alpha` gives the convex hull.
+pub fn regions(points: &[Vec2], triangulation: &Triangulation, alpha: f32) -> Vec<Region> {
+    let triangles = &triangulation.triangles;
+    let halfedges = &triangulation.halfedges;
+    let corners = |t: usize| {
+        [
+            points[triangles[3 * t]],
+            points[triangles[3 * t + 1]],
+            points[triangles[3 * t + 2]],
+        ]
+    };
+    let kept = (0..triangles.len() / 3)
+        .map(|t| {
+            let [a, b, c] = corners(t);
+            (b - a).perp_dot(c - a) != 0. && circumcenter(a, b, c).distance(a) <= alpha
+        })
+        .collect::<Vec<bool>>();
+    let Some(first) = kept.iter().position(|&kept| kept) else {
+        return vec![];
+    };
+    let winding = signed_area(&corners(first)).signum();
+
+    let boundary = |e: usize| kept[e / 3] && (halfedges[e] == EMPTY || !kept[halfedges[e] / 3]);
+
+    let mut visited = vec![false; triangles.len()];
+    let mut outlines = vec![];
+    let mut holes = vec![];
+    for start in 0..triangles.len() {
+        if visited[start] || !boundary(start) {
+            continue;
+        }
+        let mut ring = vec![];
+        let mut e = start;
+        while !visited[e] {
+            visited[e] = true;
+            ring.push(points[triangles[e]]);
+            // Turn around the end of `e` through kept triangles until the
+            // boundary continues, which keeps touching pieces apart.
+            e = next_halfedge(e);
+            while !boundary(e) {
+                e = next_halfedge(halfedges[e]);
+            }
+        }
+        // Rings wind like the triangles around outlines and the other way
+        // around holes, turning both makes outlines counterclockwise.
+        let outline = signed_area(&ring).signum() == winding;
+        if winding < 0. {
+            ring.reverse();
+        }
+        if outline {
+            outlines.push(ring);
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    let mut regions = outlines
+        .into_iter()
+        .map(|outline| Region {
+            outline,
+            holes: vec![],
+        })
+        .collect::<Vec<_>>();
+    for hole in holes {
+        // Pieces can sit inside holes of others, a hole belongs to the
+        // smallest outline around it.
+        let owner = regions
+            .iter_mut()
+            .filter(|region| contains(&region.outline, hole[0].lerp(hole[1], 0.5)))
+            .min_by(|a, b| signed_area(&a.outline).total_cmp(&signed_area(&b.outline)));
+        if let Some(region) = owner {
+            region.holes.push(hole);
+        }
+    }
+    regions
+}
+
+/// Chaikin's corner cutting on a closed ring, each round replaces every
+/// corner with two points a quarter along its sides.
+pub fn smooth(ring: &[Vec2], iterations: usize) -> Vec<Vec2> {
+    let mut ring = ring.to_vec();
+    for _ in 0..iterations {
+        let next = ring.iter().cycle().skip(1);
+        ring = ring
+            .iter()
+            .zip(next)
+            .flat_map(|(&a, &b)| [a.lerp(b, 0.25), a.lerp(b, 0.75)])
+            .collect();
+    }
+    ring
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeStyle {
+    #[default]
+    Off,
+    Filled,
+    Outlined,
+}
+
+impl ShapeStyle {
+    pub fn next(self) -> Self {
+        match self {
+            ShapeStyle::Off => ShapeStyle::Filled,
+            ShapeStyle::Filled => ShapeStyle::Outlined,
+            ShapeStyle::Outlined => ShapeStyle::Off,
+        }
+    }
+}
+
+/// How a sketch draws the alpha shape of its particles, if at all.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeSettings {
+    pub style: ShapeStyle,
+    /// Largest circumradius of the triangles in the shape, in sketch units.
+    pub alpha: f32,
+    /// Rounds of corner cutting.
+    pub smoothing: usize,
+    /// Of outlines.
+    pub weight: f32,
+    /// Where on the gradient the color is taken.
+    pub position: f32,
+    /// Multiplies the gradient's alpha.
+    pub opacity: f32,
+}
+
+impl Default for ShapeSettings {
+    fn default() -> Self {
+        ShapeSettings {
+            style: ShapeStyle::Off,
+            alpha: 150.,
+            smoothing: 3,
+            weight: 4.,
+            position: 0.,
+            opacity: 0.8,
+        }
+    }
+}
+
+impl ShapeSettings {
+    /// A button cycling the style and sliders for the rest. True if anything
+    /// changed.
+    pub fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let style = match self.style {
+            ShapeStyle::Off => "shape: off",
+            ShapeStyle::Filled => "shape: filled",
+            ShapeStyle::Outlined => "shape: outlined",
+        };
+        if ui.button(style) {
+            self.style = self.style.next();
+            changed = true;
+        }
+        if self.style == ShapeStyle::Off {
+            return changed;
+        }
+        changed |= ui.slider("shape alpha", &mut self.alpha, 0.0..=1000.);
+        changed |= ui.count("shape smoothing", &mut self.smoothing, 0..=5);
+        if self.style == ShapeStyle::Outlined {
+            changed |= ui.slider("shape weight", &mut self.weight, 0.0..=20.);
+        }
+        changed |= ui.slider("shape color", &mut self.position, 0.0..=1.);
+        changed |= ui.slider("shape opacity", &mut self.opacity, 0.0..=1.);
+        changed
+    }
+}
+
+/// Switches the style half way, the rest blends.
+impl Lerp for ShapeSettings {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        ShapeSettings {
+            style: if t < 0.5 { self.style } else { to.style },
+            alpha: self.alpha.lerp(&to.alpha, t),
+            smoothing: self.smoothing.lerp(&to.smoothing, t),
+            weight: self.weight.lerp(&to.weight, t),
+            position: self.position.lerp(&to.position, t),
+            opacity: self.opacity.lerp(&to.opacity, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::seeded;
+    use delaunator::{triangulate, Point};
+    use nannou::rand::Rng;
+
+    /// Points spread evenly over the ring between `inner` and `outer`
+    /// around `center`.
+    fn ring(seed: u64, center: Vec2, inner: f32, outer: f32, count: usize) -> Vec<Vec2> {
+        let mut rng = seeded(seed);
+        (0..count)
+            .map(|_| {
+                let radius =
+                    (inner.powi(2) + rng.gen::<f32>() * (outer.powi(2) - inner.powi(2))).sqrt();
+                let angle = rng.gen::<f32>() * TAU;
+                center + vec2(angle.cos(), angle.sin()) * radius
+            })
+            .collect()
+    }
+
+    fn shape(points: &[Vec2], alpha: f32) -> Vec<Region> {
+        let triangulation = triangulate(
+            &points
+                .iter()
+                .map(|point| Point {
+                    x: point.x as f64,
+                    y: point.y as f64,
+                })
+                .collect::<Vec<Point>>(),
+        );
+        regions(points, &triangulation, alpha)
+    }
+
+    #[test]
+    fn holes_lie_inside_their_outline() {
+        let mut points = ring(1, Vec2::ZERO, 400., 800., 2000);
+        // An island in the hole, with a hole of its own.
+        points.extend(ring(2, Vec2::ZERO, 100., 250., 500));
+        let regions = shape(&points, 60.);
+        assert_eq!(regions.len(), 2);
+        for region in regions.iter() {
+            assert!(signed_area(&region.outline) > 0.);
+            assert_eq!(region.holes.len(), 1);
+            for hole in region.holes.iter() {
+                assert!(signed_area(hole) < 0.);
+                assert!(-signed_area(hole) < signed_area(&region.outline));
+                for point in hole.iter() {
+                    assert!(contains(&region.outline, *point) || region.outline.contains(point));
+                }
+            }
+        }
+        // The island's outline sits in the big ring's hole, not its own.
+        let mut regions = regions.iter().collect::<Vec<_>>();
+        regions.sort_by(|a, b| signed_area(&a.outline).total_cmp(&signed_area(&b.outline)));
+        let (island, big) = (regions[0], regions[1]);
+        assert!(contains(&big.holes[0], island.outline[0]));
+        assert!(!contains(&island.holes[0], big.holes[0][0]));
+    }
+
+    #[test]
+    fn area_leaves_out_the_holes() {
+        let points = ring(3, Vec2::ZERO, 400., 800., 3000);
+        let regions = shape(&points, 60.);
+        assert_eq!(regions.len(), 1);
+        let ring_area = PI * (800f32.powi(2) - 400f32.powi(2));
+        assert!((regions[0].area() - ring_area).abs() < ring_area * 0.05);
+    }
+
+    #[test]
+    fn infinite_alpha_is_the_convex_hull() {
+        let points = ring(4, Vec2::ZERO, 400., 800., 500);
+        let regions = shape(&points, f32::INFINITY);
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].holes.is_empty());
+        let outline = &regions[0].outline;
+        for point in points.iter() {
+            assert!(contains(outline, *point) || outline.contains(point));
+        }
+    }
+
+    #[test]
+    fn distant_clusters_are_separate_regions() {
+        let mut points = ring(5, vec2(-1000., 0.), 0., 200., 300);
+        points.extend(ring(6, vec2(1000., 0.), 0., 200., 300));
+        assert_eq!(shape(&points, 60.).len(), 2);
+        assert!(shape(&points, 1.).is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::registry;
+use crate::sweep::Sweep;
+use crate::video::Format;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: <sketch> [--seed <u64>] [--params <toml>] [--steps <n>]
+    [--render <png> [--frames <n>] [--size <width>x<height>] [--sweep <key>=<from>:<to>:<count>]...]
+    [--video <path> [--duration <seconds>] [--fps <n>] [--format y4m|raw|gif]]
+    [--poster <png> [--frames <n>] [--size <width>x<height>] [--tile <pixels>] [--scale <pixels per unit>]]";
+
+/// Command line flags shared by all sketches.
+///
+/// `--seed <u64>` seeds the sketch's randomness.
+/// `--render <png>` renders headless instead of opening a window, for
+/// `--frames <n>` frames at `--size <width>x<height>`.
+/// `--video <path>` encodes `--duration <seconds>` at `--fps <n>` instead,
+/// as `--format y4m|raw|gif` or guessed from the extension.
+/// `--poster <png>` renders `--frames <n>` frames at `--size` in tiles of
+/// `--tile <pixels>`, at `--scale <pixels per unit>`.
+/// `--params <toml>` reads the sketch's parameters from another file.
+/// `--steps <n>` runs the simulation `n` steps ahead before the first frame.
+/// `--sweep <key>=<from>:<to>:<count>`, up to twice, renders a contact sheet
+/// of parameter variants to `--render`.
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub seed: Option<u64>,
+    pub render: Option<PathBuf>,
+    pub frames: Option<u64>,
+    pub size: Option<(u32, u32)>,
+    pub video: Option<PathBuf>,
+    pub format: Option<Format>,
+    pub fps: Option<u32>,
+    pub duration: Option<f32>,
+    pub poster: Option<PathBuf>,
+    pub scale: Option<f32>,
+    pub tile: Option<u32>,
+    pub params: Option<PathBuf>,
+    pub steps: Option<u64>,
+    pub sweeps: Vec<Sweep>,
+}
+
+impl Args {
+    /// The sketch's flags, printing usage and exiting on bad ones.
+    pub fn parse() -> Self {
+        Self::parse_from(registry::args()).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        })
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--seed" => parsed.seed = Some(number(&arg, value?, "a u64")?),
+                "--render" => parsed.render = Some(value?.into()),
+                "--frames" => parsed.frames = Some(number(&arg, value?, "a u64")?),
+                "--size" => {
+                    let value = value?;
+                    parsed.size = Some(parse_size(&value).ok_or_else(|| {
+                        format!(
+                            "--size must be <width>x<height>, both at least 1, not {}",
+                            value
+                        )
+                    })?);
+                }
+                "--video" => parsed.video = Some(value?.into()),
+                "--format" => {
+                    let value = value?;
+                    parsed.format = Some(Format::parse(&value).ok_or_else(|| {
+                        format!("--format must be y4m, raw or gif, not {}", value)
+                    })?);
+                }
+                "--fps" => parsed.fps = Some(positive(&arg, value?, "a u32")?),
+                "--duration" => parsed.duration = Some(number(&arg, value?, "seconds")?),
+                "--poster" => parsed.poster = Some(value?.into()),
+                "--scale" => parsed.scale = Some(positive(&arg, value?, "a number")?),
+                "--tile" => parsed.tile = Some(positive(&arg, value?, "a u32")?),
+                "--params" => parsed.params = Some(value?.into()),
+                "--steps" => parsed.steps = Some(number(&arg, value?, "a u64")?),
+                "--sweep" => {
+                    let value = value?;
+                    parsed.sweeps.push(Sweep::parse(&value).ok_or_else(|| {
+                        format!(
+                            "--sweep must be <key>=<from>:<to>:<count> or <key>=<a>,<b>, not {}",
+                            value
+                        )
+                    })?);
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        if parsed.sweeps.len() > 2 {
+            return Err("at most two --sweep".to_owned());
+        }
+        if !parsed.sweeps.is_empty() && parsed.render.is_none() {
+            return Err("--sweep renders to --render, which is missing".to_owned());
+        }
+        Ok(parsed)
+    }
+}
+
+fn number<T: FromStr>(arg: &str, value: String, expected: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be {}, not {}", arg, expected, value))
+}
+
+/// A `number` above zero.
+fn positive<T: FromStr + PartialOrd + Default>(
+    arg: &str,
+    value: String,
+    expected: &str,
+) -> Result<T, String> {
+    let number = number(arg, value.clone(), expected)?;
+    if number > T::default() {
+        Ok(number)
+    } else {
+        Err(format!("{} must be above 0, not {}", arg, value))
+    }
+}
+
+/// `<width>x<height>`, neither of them zero.
+pub fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse_from(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn parses_flags() {
+        let args = parse("--seed 7 --render out.png --size 640x360 --frames 3").unwrap();
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.render, Some(PathBuf::from("out.png")));
+        assert_eq!(args.size, Some((640, 360)));
+        assert_eq!(args.frames, Some(3));
+    }
+
+    #[test]
+    fn rejects_bad_flags() {
+        assert!(parse("--seed").is_err());
+        assert!(parse("--seed x").is_err());
+        assert!(parse("--bogus 1").is_err());
+        assert!(parse("--sweep speed=0:1:3").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_sizes() {
+        assert_eq!(parse_size("0x0"), None);
+        assert_eq!(parse_size("640x0"), None);
+        assert_eq!(parse_size("640"), None);
+        assert!(parse("--render z.png --size 0x0").is_err());
+    }
+
+    #[test]
+    fn rejects_zero_fps() {
+        assert!(parse("--video v.gif --fps 0").is_err());
+        assert_eq!(parse("--video v.gif --fps 30").unwrap().fps, Some(30));
+    }
+
+    #[test]
+    fn rejects_non_positive_tiles_and_scales() {
+        assert!(parse("--poster p.png --tile 0").is_err());
+        assert!(parse("--poster p.png --scale 0").is_err());
+        assert!(parse("--poster p.png --scale -1").is_err());
+        assert!(parse("--poster p.png --scale NaN").is_err());
+        let args = parse("--poster p.png --tile 512 --scale 2.5").unwrap();
+        assert_eq!((args.tile, args.scale), (Some(512), Some(2.5)));
+    }
+}
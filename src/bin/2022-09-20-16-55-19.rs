@@ -114,7 +114,7 @@ fn key_released(app: &App, model: &mut Model, key: Key) {
     match key {
         Key::S => {
             app.main_window()
-                .capture_frame(app.exe_name().unwrap() + ".png");
+                .capture_frame(rustyart::registry::name(app) + ".png");
         }
         Key::Key1 => {
             model.filled = false;
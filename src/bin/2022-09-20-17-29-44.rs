@@ -1,7 +1,7 @@
 use nannou::geom::*;
 use nannou::prelude::*;
 use nannou::rand::random_f32;
-use ordered_float::OrderedFloat;
+use rustyart::spatial::Grid;
 use std::f32::consts::PI;
 use std::iter::*;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -19,35 +19,6 @@ struct Particle {
     draw_position: Vec2,
 }
 
-trait RankeableByDistance {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized;
-}
-
-impl RankeableByDistance for Particle {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others;
-        ranking
-            .sort_by_cached_key(|particle| OrderedFloat(particle.position.distance(self.position)));
-        ranking
-    }
-}
-
-impl RankeableByDistance for Vec2 {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others;
-        ranking.sort_by_cached_key(|particle| OrderedFloat(particle.distance(*self)));
-        ranking
-    }
-}
-
 #[derive(Debug)]
 struct Model {
     freeze: bool,
@@ -96,13 +67,16 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     let hunters = model.hunters.clone();
     let runners = model.runners.clone();
 
-    for hunter in model.hunters.iter_mut() {
+    let particle_grid = Grid::new(particles.iter().map(|particle| particle.position));
+    let hunter_grid = Grid::new(hunters.iter().map(|hunter| hunter.position));
+    let runner_grid = Grid::new(runners.iter().map(|runner| runner.position));
+
+    for (i, hunter) in model.hunters.iter_mut().enumerate() {
         if hunter.teleport {
             hunter.teleport = false;
             loop {
                 hunter.position = random_point_in_radius(&ORIGIN, RADIUS);
-                let ranking = hunter.rank_by_distance(particles.clone());
-                let other = ranking.get(1).unwrap();
+                let other = &particles[particle_grid.nearest_k(hunter.position, 2)[1]];
                 let distance = hunter.position.distance(other.position);
                 if !(distance <= other.radius + hunter.radius) {
                     break;
@@ -113,13 +87,10 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
 
         hunter.draw_position = hunter.position;
 
-        let mut ally_ranking = hunter.rank_by_distance(hunters.clone());
-        ally_ranking.remove(0);
-        let ally = ally_ranking.first().unwrap();
+        let ally = &hunters[hunter_grid.nearest_excluding(hunter.position, i).unwrap()];
         let ally_distance = hunter.position.distance(ally.position);
 
-        let enemy_ranking = hunter.rank_by_distance(runners.clone());
-        let enemy = enemy_ranking.first().unwrap();
+        let enemy = &runners[runner_grid.nearest(hunter.position).unwrap()];
         let enemy_distance = hunter.position.distance(enemy.position);
 
         let origin_distance = hunter.position.distance(ORIGIN);
@@ -136,13 +107,12 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
             + random_point_in_radius(&ORIGIN, 0.2)
     }
 
-    for runner in model.runners.iter_mut() {
+    for (i, runner) in model.runners.iter_mut().enumerate() {
         if runner.teleport {
             runner.teleport = false;
             loop {
                 runner.position = random_point_in_radius(&ORIGIN, RADIUS);
-                let ranking = runner.rank_by_distance(particles.clone());
-                let other = ranking.get(1).unwrap();
+                let other = &particles[particle_grid.nearest_k(runner.position, 2)[1]];
                 let distance = runner.position.distance(other.position);
                 if !(distance <= other.radius + runner.radius) {
                     break;
@@ -154,13 +124,10 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
 
         runner.draw_position = runner.position;
 
-        let mut ally_ranking = runner.rank_by_distance(runners.clone());
-        ally_ranking.remove(0);
-        let ally = ally_ranking.first().unwrap();
+        let ally = &runners[runner_grid.nearest_excluding(runner.position, i).unwrap()];
         let ally_distance = runner.position.distance(ally.position);
 
-        let enemy_ranking = runner.rank_by_distance(hunters.clone());
-        let enemy = enemy_ranking.first().unwrap();
+        let enemy = &hunters[hunter_grid.nearest(runner.position).unwrap()];
         let enemy_distance = runner.position.distance(enemy.position);
 
         let origin_distance = runner.position.distance(ORIGIN);
@@ -221,7 +188,7 @@ fn key_released(app: &App, model: &mut Model, key: Key) {
             let now = SystemTime::now();
             app.main_window().capture_frame(
                 "out/".to_owned()
-                    + &app.exe_name().unwrap()
+                    + &rustyart::registry::name(app)
                     + "#"
                     + &now
                         .duration_since(UNIX_EPOCH)
@@ -1,10 +1,11 @@
-use nannou::geom::*;
 use nannou::prelude::*;
-use nannou::rand::random_f32;
-use ordered_float::OrderedFloat;
-use std::f32::consts::PI;
-use std::iter::*;
-use std::time::{SystemTime, UNIX_EPOCH};
+use nannou::rand::rngs::StdRng;
+use rustyart::capture;
+use rustyart::clock::Clock;
+use rustyart::input::{self, Controls};
+use rustyart::render::{Canvas, DrawCanvas};
+use rustyart::sampling::{random_point_in_radius, seed_from_args, seeded};
+use rustyart::spatial::Grid;
 
 fn main() {
     nannou::app(model).update(update).run();
@@ -18,56 +19,11 @@ struct Particle {
     draw_position: Vec2,
 }
 
-trait RankeableByDrawDistance {
-    fn rank_by_draw_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized;
-}
-
-impl RankeableByDrawDistance for Particle {
-    fn rank_by_draw_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others;
-        ranking
-            .sort_by_cached_key(|particle| OrderedFloat(particle.draw_position.distance(self.draw_position)));
-        ranking
-    }
-}
-
-trait RankeableByDistance {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized;
-}
-
-impl RankeableByDistance for Particle {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others;
-        ranking
-            .sort_by_cached_key(|particle| OrderedFloat(particle.position.distance(self.position)));
-        ranking
-    }
-}
-
-impl RankeableByDistance for Vec2 {
-    fn rank_by_distance(&self, others: Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others;
-        ranking.sort_by_cached_key(|vec| OrderedFloat(vec.distance(*self)));
-        ranking
-    }
-}
-
 #[derive(Debug)]
 struct Model {
-    freeze: bool,
+    controls: Controls,
+    clock: Clock,
+    rng: StdRng,
     hunters: Vec<Particle>,
     runners: Vec<Particle>,
 }
@@ -83,48 +39,62 @@ fn model(app: &App) -> Model {
         .build()
         .unwrap();
 
+    let seed = seed_from_args();
+    let mut rng = seeded(seed);
+
+    let hunters = (0..200)
+        .map(|_| Particle {
+            position: random_point_in_radius(&mut rng, &ORIGIN, RADIUS),
+            radius: 10.0,
+            teleport: false,
+            draw_position: ORIGIN,
+        })
+        .collect::<Vec<Particle>>();
+    let runners = (0..500)
+        .map(|_| Particle {
+            position: random_point_in_radius(&mut rng, &ORIGIN, RADIUS),
+            radius: 10.0,
+            teleport: false,
+            draw_position: ORIGIN,
+        })
+        .collect::<Vec<Particle>>();
+
     Model {
-        freeze: false,
-        hunters: (0..200)
-            .map(|_| Particle {
-                position: random_point_in_radius(&ORIGIN, RADIUS),
-                radius: 10.0,
-                teleport: false,
-                draw_position: ORIGIN,
-            })
-            .collect::<Vec<Particle>>(),
-        runners: (0..500)
-            .map(|_| Particle {
-                position: random_point_in_radius(&ORIGIN, RADIUS),
-                radius: 10.0,
-                teleport: false,
-                draw_position: ORIGIN,
-            })
-            .collect::<Vec<Particle>>(),
+        controls: Controls::new(seed),
+        clock: Clock::default(),
+        rng,
+        hunters,
+        runners,
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-
-    if model.freeze {
-        return;
+fn update(app: &App, model: &mut Model, update: Update) {
+    for _ in 0..model.clock.advance(update.since_last) {
+        model.clock.tick();
+        step(model);
     }
+    input::capture_requested(app, &mut model.controls, &model.clock);
+}
 
-    let mut particles = model.hunters.clone();
-    particles.append(&mut model.runners.clone());
-
+fn step(model: &mut Model) {
     let hunters = model.hunters.clone();
     let runners = model.runners.clone();
 
+    let mut particles = hunters.clone();
+    particles.append(&mut runners.clone());
+
+    let particle_grid = Grid::new(particles.iter().map(|particle| particle.position));
+    let hunter_grid = Grid::new(hunters.iter().map(|hunter| hunter.position));
+    let runner_grid = Grid::new(runners.iter().map(|runner| runner.position));
+
     for hunter in model.hunters.iter_mut() {
         if hunter.teleport {
             hunter.teleport = false;
             loop {
-                hunter.position = random_point_in_radius(&ORIGIN, RADIUS);
-                let ranking = hunter.rank_by_distance(particles.clone());
-                let other = ranking.get(1).unwrap();
+                hunter.position = random_point_in_radius(&mut model.rng, &ORIGIN, RADIUS);
+                let other = &particles[particle_grid.nearest_k(hunter.position, 2)[1]];
                 let distance = hunter.position.distance(other.position);
-                if !(distance <= other.radius + hunter.radius) {
+                if distance > other.radius + hunter.radius {
                     break;
                 };
             }
@@ -133,8 +103,7 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
 
         hunter.draw_position = hunter.position;
 
-        let enemy_ranking = hunter.rank_by_distance(runners.clone());
-        let enemy = enemy_ranking.first().unwrap();
+        let enemy = &runners[runner_grid.nearest(hunter.position).unwrap()];
         let enemy_distance = hunter.position.distance(enemy.position);
 
         let origin_distance = hunter.position.distance(ORIGIN);
@@ -147,15 +116,14 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
         hunter.position -= (hunter.position - enemy.position).normalize() * 1.0;
     }
 
-    for runner in model.runners.iter_mut() {
+    for (i, runner) in model.runners.iter_mut().enumerate() {
         if runner.teleport {
             runner.teleport = false;
             loop {
-                runner.position = random_point_in_radius(&ORIGIN, RADIUS);
-                let ranking = runner.rank_by_distance(particles.clone());
-                let other = ranking.get(1).unwrap();
+                runner.position = random_point_in_radius(&mut model.rng, &ORIGIN, RADIUS);
+                let other = &particles[particle_grid.nearest_k(runner.position, 2)[1]];
                 let distance = runner.position.distance(other.position);
-                if !(distance <= other.radius + runner.radius) {
+                if distance > other.radius + runner.radius {
                     break;
                 };
             }
@@ -164,12 +132,10 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
 
         runner.draw_position = runner.position;
 
-        let ally_ranking = runner.rank_by_distance(runners.clone());
-        let ally = ally_ranking.get(1).unwrap().clone();
+        let ally = &runners[runner_grid.nearest_excluding(runner.position, i).unwrap()];
         let ally_distance = runner.position.distance(ally.position);
 
-        let enemy_ranking = runner.rank_by_distance(hunters.clone());
-        let enemy = enemy_ranking.first().unwrap();
+        let enemy = &hunters[hunter_grid.nearest(runner.position).unwrap()];
         let enemy_distance = runner.position.distance(enemy.position);
 
         let origin_distance = runner.position.distance(ORIGIN);
@@ -188,90 +154,59 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
 
 fn view(app: &App, model: &Model, frame: Frame) {
 
-    if model.freeze {
+    if model.clock.frozen() {
         return;
     }
 
-    let draw = app.draw();
-    let win = app.window_rect();
-    if app.keys.down.contains(&Key::Delete) {
-        draw.background().color(BLACK);
+    let mut canvas = DrawCanvas::new(app);
+    if input::clear_requested(app) {
+        canvas.background(BLACK);
     }
 
-    let win_p = win.pad(0.0);
-    draw.rect()
-        .xy(win_p.xy())
-        .wh(win_p.wh())
-        .rgba(0.0, 0.0, 0.0, 0.03);
-
-    for hunter in model.hunters.iter() {
-        let mut ally_ranking = hunter.rank_by_draw_distance(model.hunters.clone());
-        ally_ranking.remove(0);
-        for ally in ally_ranking.iter().take_while(|ally| hunter.draw_position.distance(ally.draw_position) < 200.0 ) {
-            draw.line()
-                .color(RED)
-                .weight(3.0)
-                .caps_round()
-                .points(hunter.draw_position, ally.draw_position);
+    draw(model, &mut canvas);
+
+    input::draw_info(app, &canvas.draw, &model.controls, &model.clock);
+
+    canvas.draw.to_frame(app, &frame).unwrap();
+}
+
+fn draw(model: &Model, canvas: &mut impl Canvas) {
+    canvas.rect(canvas.bounds(), rgba(0.0, 0.0, 0.0, 0.03));
+
+    let hunter_grid = Grid::new(model.hunters.iter().map(|hunter| hunter.draw_position));
+    for (i, hunter) in model.hunters.iter().enumerate() {
+        for j in hunter_grid.within_radius(hunter.draw_position, 200.0) {
+            if i == j {
+                continue;
+            }
+            canvas.line(hunter.draw_position, model.hunters[j].draw_position, 3.0, RED);
         }
     }
 
-    for runner in model.runners.iter() {
-        let mut ally_ranking = runner.rank_by_draw_distance(model.runners.clone());
-        ally_ranking.remove(0);
-        for ally in ally_ranking.iter().take_while(|ally| runner.draw_position.distance(ally.draw_position) < 150.0 ) {
-            draw.line()
-                .color(BLUE)
-                .weight(3.0)
-                .caps_round()
-                .points(runner.draw_position, ally.draw_position);
+    let runner_grid = Grid::new(model.runners.iter().map(|runner| runner.draw_position));
+    for (i, runner) in model.runners.iter().enumerate() {
+        for j in runner_grid.within_radius(runner.draw_position, 150.0) {
+            if i == j {
+                continue;
+            }
+            canvas.line(runner.draw_position, model.runners[j].draw_position, 3.0, BLUE);
         }
     }
     
     if true {
         for hunter in model.hunters.iter() {
-            draw.ellipse()
-                .radius(hunter.radius)
-                .xy(hunter.draw_position)
-                .color(YELLOW);
+            canvas.ellipse(hunter.draw_position, hunter.radius, YELLOW);
         }
 
         for runner in model.runners.iter() {
-            draw.ellipse()
-                .radius(runner.radius)
-                .xy(runner.draw_position)
-                .color(GREEN);
+            canvas.ellipse(runner.draw_position, runner.radius, GREEN);
         }
     }
-
-    draw.to_frame(app, &frame).unwrap();
 }
 
 fn key_released(app: &App, model: &mut Model, key: Key) {
-    match key {
-        Key::S => {
-            let now = SystemTime::now();
-            app.main_window().capture_frame(
-                "out/".to_owned()
-                    + &app.exe_name().unwrap()
-                    + "#"
-                    + &now
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis()
-                        .to_string()
-                    + ".png",
-            );
-        }
-        Key::F => {
-            model.freeze = !model.freeze;
-        }
-        _ => (),
+    if key == Key::V {
+        capture::capture_svg(app, model.controls.seed, |svg| draw(model, svg));
     }
-}
-
-fn random_point_in_radius(o: &Vec2, r: f32) -> Vec2 {
-    let r = r * random_f32().sqrt();
-    let t = random_f32() * 2.0 * PI;
-    vec2(o.x + r * t.cos(), o.y + r * t.sin())
+    input::key_released(&mut model.controls, &mut model.clock, key);
 }
@@ -4,7 +4,7 @@ use nannou::ease::*;
 use nannou::geom::*;
 use nannou::prelude::*;
 use nannou::rand::random_f32;
-use ordered_float::OrderedFloat;
+use rustyart::spatial::Grid;
 use std::f32::consts::PI;
 use std::iter::*;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -34,19 +34,6 @@ struct Model {
     links: Vec<Link>,
 }
 
-trait RankeableByDistance {
-    fn rank_by_distance(&self, others: &Vec<Self>) -> Vec<Self> where Self: Sized;
-}
-
-impl RankeableByDistance for Particle {
-    fn rank_by_distance(&self, others: &Vec<Self>) -> Vec<Self> where Self: Sized {
-        let mut ranking = others.clone();
-        ranking
-            .sort_by_cached_key(|particle| OrderedFloat(particle.position.distance(self.position)));
-        ranking
-    }
-}
-
 const ORIGIN: Vec2 = Vec2::ZERO;
 const RADIUS: f32 = 2000.;
 const BACKGROUND_COLOR: Rgba = Alpha {
@@ -92,8 +79,9 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     }
 
     let particles = model.particles.clone();
+    let grid = Grid::new(particles.iter().map(|particle| particle.position));
 
-    for particle in model.particles.iter_mut() {
+    for (i, particle) in model.particles.iter_mut().enumerate() {
         if particle.position.distance(particle.target) <= particle.radius {
             loop {
                 particle.target =
@@ -104,8 +92,7 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
             }
         }
 
-        let neighbours = particle.rank_by_distance(&particles);
-        let neighbour = neighbours.get(1).unwrap();
+        let neighbour = &particles[grid.nearest_excluding(particle.position, i).unwrap()];
         let neighbour_distance = particle.position.distance(neighbour.position);
         let neighbour_distance_mapped = map_range::<f32, f32>(neighbour_distance, 0., 100., 0., 1.).clamp(0., 1.);
 
@@ -206,7 +193,7 @@ fn key_released(app: &App, model: &mut Model, key: Key) {
             let now = SystemTime::now();
             app.main_window().capture_frame(
                 "out/".to_owned()
-                    + &app.exe_name().unwrap()
+                    + &rustyart::registry::name(app)
                     + "#"
                     + &now
                         .duration_since(UNIX_EPOCH)
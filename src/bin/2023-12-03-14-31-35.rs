@@ -1,270 +1,449 @@
-use delaunator::{next_halfedge, triangulate, Point, EMPTY};
+use delaunator::Triangulation;
 use nannou::color;
 use nannou::color::*;
 use nannou::ease::*;
-use nannou::geom::*;
 use nannou::prelude::*;
-use nannou::rand::random_f32;
-use ordered_float::OrderedFloat;
-use std::f32::consts::PI;
-use std::iter::*;
-use std::time::{SystemTime, UNIX_EPOCH};
+use nannou::rand::rngs::StdRng;
+use nannou::rand::Rng;
+use rustyart::alpha_shape::{self, Region, ShapeSettings, ShapeStyle};
+use rustyart::args::Args;
+use rustyart::capture;
+use rustyart::graph::GraphSettings;
+use rustyart::links::{triangulate_particles, LinkTracker};
+use rustyart::gui::Ui;
+use rustyart::params::{Params, ParamsFile, Stop, Validate};
+use rustyart::particles::Particle;
+use rustyart::presets::{Lerp, Presets};
+use rustyart::plot::{Plot, PlotSettings};
+use rustyart::render::Canvas;
+use rustyart::runner::{self, Context, Event, Runner, Sketch};
+use rustyart::sampling::{random_point_in_radius, seed_from_args, seeded};
+use rustyart::spatial::Grid;
+use rustyart::sweep::ContactSheet;
+use rustyart::trails::Fade;
+use rustyart::triangles::TriangleSettings;
+use rustyart::voronoi::{self, Cell, CellColor, CellSettings, CellStyle};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 fn main() {
-    nannou::app(model).update(update).run();
+    if let Some(sheet) = ContactSheet::from_args(&Args::parse()) {
+        let seed = seed_from_args();
+        let params = ParamsFile::for_sketch(file!(), default_params()).load();
+        let title = format!("2023-12-03-14-31-35  seed {}  step {}", seed, sheet.steps);
+        sheet.run(&title, &params, |params, raster| {
+            let mut context = Context::new(seed);
+            let params_file = ParamsFile::for_sketch(file!(), default_params());
+            let model = Model::with_params(&mut context, params, params_file);
+            let mut runner = Runner::new(context, model);
+            for _ in 0..sheet.steps {
+                runner.step();
+            }
+            for _ in 0..sheet.frames {
+                runner.advance_exact(Duration::from_secs_f32(1. / 60.));
+                runner.render(raster);
+            }
+        });
+        return;
+    }
+    runner::run::<Model>();
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Particle {
-    position: Vec2,
-    radius: f32,
-    target: Vec2,
-    target_since: SystemTime,
+#[derive(Clone, Debug)]
+struct Model {
+    rng: StdRng,
+    particles: Vec<Particle>,
+    links: LinkTracker,
+    cells: Vec<Cell>,
+    regions: Vec<Region>,
+    triangulation: Triangulation,
+    plot_history: Option<Plot>,
+    /// Step of the last frame drawn into `plot_history`.
+    plot_history_step: u64,
+    params: Settings,
+    params_file: ParamsFile<Settings>,
+    presets: Presets<Settings>,
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Link {
-    a: usize,
-    b: usize,
-    since: SystemTime,
+/// The shared particle parameters and the layers only this sketch draws.
+/// The shared ones are flattened, so the files keep a single level of keys.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    #[serde(flatten)]
+    base: Params,
+    /// Simulation seconds for trails to fade to half, 0 fades them by drawing
+    /// the translucent background over every frame instead.
+    #[serde(default)]
+    trail_half_life: f32,
+    /// Which proximity graph links follow.
+    #[serde(default)]
+    graph: GraphSettings,
+    /// Voronoi cells drawn under the links.
+    #[serde(default)]
+    cells: CellSettings,
+    /// Delaunay triangles filled under the cells.
+    #[serde(default)]
+    triangles: TriangleSettings,
+    /// Alpha shape outline of the particle cloud, drawn under the links.
+    #[serde(default)]
+    shape: ShapeSettings,
 }
 
-#[derive(Clone, Debug)]
-struct Model {
-    freeze: bool,
-    particles: Vec<Particle>,
-    links: Vec<Link>,
+impl Settings {
+    fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = self.base.edit(ui);
+        changed |= ui.slider("trail half-life", &mut self.trail_half_life, 0.0..=5.);
+        changed |= self.graph.edit(ui);
+        changed |= self.cells.edit(ui);
+        changed |= self.triangles.edit(ui);
+        changed |= self.shape.edit(ui);
+        changed
+    }
 }
 
-trait RankeableByDistance {
-    fn rank_by_distance(&self, others: &Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized;
+impl Validate for Settings {
+    fn validate(self) -> Result<Self, String> {
+        if self.graph.k == 0 {
+            return Err("graph.k must be at least 1".to_owned());
+        }
+        if self.trail_half_life < 0. || self.trail_half_life.is_nan() {
+            return Err("trail_half_life must not be negative".to_owned());
+        }
+        Ok(Settings {
+            base: self.base.validate()?,
+            ..self
+        })
+    }
 }
 
-impl RankeableByDistance for Particle {
-    fn rank_by_distance(&self, others: &Vec<Self>) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let mut ranking = others.clone();
-        ranking
-            .sort_by_cached_key(|particle| OrderedFloat(particle.position.distance(self.position)));
-        ranking
+impl Lerp for Settings {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Settings {
+            base: self.base.lerp(&to.base, t),
+            trail_half_life: self.trail_half_life.lerp(&to.trail_half_life, t),
+            graph: self.graph.lerp(&to.graph, t),
+            cells: self.cells.lerp(&to.cells, t),
+            triangles: self.triangles.lerp(&to.triangles, t),
+            shape: self.shape.lerp(&to.shape, t),
+        }
     }
 }
 
 const ORIGIN: Vec2 = Vec2::ZERO;
 const RADIUS: f32 = 1600.;
-const BACKGROUND_COLOR: Rgba = Alpha {
-    color: Rgb {
-        red: 0.,
-        green: 0.,
-        blue: 0.,
-        standard: std::marker::PhantomData,
-    },
-    alpha: 0.018,
-};
-const PARTICLE_RADIUS: f32 = 70.;
-const PARTICLE_NUMBER: i32 = 250;
-const PARTICLE_SPEED: f32 = 0.7;
-const PARTICLE_TARGET_RADIUS: f32 = 420.;
-const PARTICLE_TARGET_TIME: f32 = 12.;
-const PARTICLE_DISTANCE_MAX: f32 = 500.;
-const LINE_WIGHT: f32 = 13.;
-
-fn model(app: &App) -> Model {
-    app.new_window()
-        .fullscreen()
-        .view(view)
-        .key_released(key_released)
-        .build()
-        .unwrap();
-
-    Model {
-        freeze: false,
-        particles: (0..PARTICLE_NUMBER)
-            .map(|_| Particle {
-                position: random_point_in_radius(&ORIGIN, RADIUS*2.),
-                radius: PARTICLE_RADIUS,
-                target: random_point_in_radius(&ORIGIN, RADIUS/2.),
-                target_since: SystemTime::now(),
-            })
-            .collect::<Vec<Particle>>(),
-        links: vec![],
+const LINK_FADE_OUT: f32 = 1.7;
+/// Steps between the frames drawn into the history plot, links live much
+/// longer so they still show up in it.
+const HISTORY_STEPS: u64 = 30;
+/// Strokes after which the history plot stops recording.
+const HISTORY_STROKES: usize = 200_000;
+
+fn default_params() -> Settings {
+    Settings {
+        base: Params {
+            particle_radius: 70.,
+            particle_number: 250,
+            particle_speed: 0.7,
+            particle_target_radius: 420.,
+            particle_target_time: 12.,
+            particle_distance_max: 500.,
+            line_weight: 13.,
+            background: [0., 0., 0., 0.018],
+            gradient: vec![
+                Stop::new(0.0, 41., 1., 0.5),
+                Stop::new(0.65, 0., 1., 0.5),
+                Stop::new(1.0, 234., 1., 0.5),
+            ],
+        },
+        trail_half_life: 0.,
+        graph: GraphSettings::default(),
+        cells: CellSettings::default(),
+        triangles: TriangleSettings::default(),
+        shape: ShapeSettings::default(),
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    if model.freeze {
-        return;
+impl Sketch for Model {
+    fn init(context: &mut Context) -> Self {
+        let mut params_file = ParamsFile::for_sketch(file!(), default_params());
+        let params = params_file.load();
+        Model::with_params(context, params, params_file)
     }
 
-    let particles = model.particles.clone();
-
-    for particle in model.particles.iter_mut() {
-        if particle.position.distance(particle.target) <= particle.radius
-            || particle.target_since.elapsed().unwrap().as_secs_f32()
-                > PARTICLE_TARGET_TIME + (PARTICLE_TARGET_TIME * random_f32())
-        {
-            loop {
-                particle.target =
-                    random_point_in_radius(&ORIGIN, PARTICLE_TARGET_RADIUS);
-                if particle.target.distance(ORIGIN) <= RADIUS {
-                    break;
-                };
+    fn update(&mut self, context: &Context) {
+        let now = context.clock.time();
+        let params = &self.params;
+
+        let particles = self.particles.clone();
+        let grid = Grid::new(particles.iter().map(|particle| particle.position));
+
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            if particle.position.distance(particle.target) <= particle.radius
+                || (now - particle.target_since) as f32
+                    > params.base.particle_target_time + (params.base.particle_target_time * self.rng.gen::<f32>())
+            {
+                loop {
+                    particle.target =
+                        random_point_in_radius(&mut self.rng, &ORIGIN, params.base.particle_target_radius);
+                    if particle.target.distance(ORIGIN) <= RADIUS {
+                        break;
+                    };
+                }
+                particle.position = random_point_in_radius(&mut self.rng, &ORIGIN, RADIUS*2.);
+                particle.target_since = now;
             }
-            particle.position = random_point_in_radius(&ORIGIN, RADIUS*2.);
-            particle.target_since = SystemTime::now();
-        }
 
-        if particle.position.distance(ORIGIN) <= PARTICLE_RADIUS*2. {
-            particle.position = random_point_in_radius(&ORIGIN, RADIUS*2.);
-            particle.target_since = SystemTime::now();
-            particle.target = random_point_in_radius(&ORIGIN, PARTICLE_TARGET_RADIUS);
-        }
+            if particle.position.distance(ORIGIN) <= params.base.particle_radius*2. {
+                particle.position = random_point_in_radius(&mut self.rng, &ORIGIN, RADIUS*2.);
+                particle.target_since = now;
+                particle.target = random_point_in_radius(&mut self.rng, &ORIGIN, params.base.particle_target_radius);
+            }
 
-        let neighbours = particle.rank_by_distance(&particles);
-        let neighbour = neighbours.get(1).unwrap();
-        let neighbour_distance = particle.position.distance(neighbour.position);
-        let neighbour_distance_mapped =
-            1. - map_range::<f32, f32>(neighbour_distance, 0., 2.*PARTICLE_RADIUS, 0., 1.).clamp(0., 1.);
-        let neighbour_distance_mapped_eased =
-            1. - cubic::ease_out(neighbour_distance_mapped, 0., 1., 1.);
+            let neighbour = &particles[grid.nearest_excluding(particle.position, i).unwrap()];
+            let neighbour_distance = particle.position.distance(neighbour.position);
+            let neighbour_distance_mapped =
+                1. - map_range::<f32, f32>(neighbour_distance, 0., 2.*params.base.particle_radius, 0., 1.).clamp(0., 1.);
+            let neighbour_distance_mapped_eased =
+                1. - cubic::ease_out(neighbour_distance_mapped, 0., 1., 1.);
 
-        let target_vec =
-            (particle.target - particle.position).normalize() * neighbour_distance_mapped_eased;
-        let neighbour_vec = (particle.position - neighbour.position).normalize()
-            * (1. - neighbour_distance_mapped_eased);
+            let target_vec =
+                (particle.target - particle.position).normalize() * neighbour_distance_mapped_eased;
+            let neighbour_vec = (particle.position - neighbour.position).normalize()
+                * (1. - neighbour_distance_mapped_eased);
 
-        particle.position += (target_vec + neighbour_vec).normalize() * PARTICLE_SPEED;
-    }
+            particle.position += (target_vec + neighbour_vec).normalize() * params.base.particle_speed;
+        }
 
-    let points = particles
-        .iter()
-        .map(|particle| Point {
-            x: particle.position.x.to_f64().unwrap(),
-            y: particle.position.y.to_f64().unwrap(),
-        })
-        .collect::<Vec<Point>>();
+        let triangulation = triangulate_particles(&particles);
+
+        let positions = particles.iter().map(|particle| particle.position).collect::<Vec<_>>();
+        self.links.update(params.graph.edges(&positions, &triangulation), now);
+        self.cells = match params.cells.style {
+            CellStyle::Off => vec![],
+            _ => voronoi::cells(&positions, &triangulation, ORIGIN, RADIUS),
+        };
+        self.regions = match params.shape.style {
+            ShapeStyle::Off => vec![],
+            _ => alpha_shape::regions(&positions, &triangulation, params.shape.alpha),
+        };
+        self.triangulation = triangulation;
+    }
 
-    let triangulation = triangulate(&points);
+    fn trails(&self) -> Option<Fade> {
+        let [red, green, blue, _] = self.params.base.background;
+        (self.params.trail_half_life > 0.)
+            .then(|| Fade::new(self.params.trail_half_life, color::rgb(red, green, blue)))
+    }
 
-    let mut links: Vec<Link> = vec![];
+    fn draw(&self, context: &Context, canvas: &mut impl Canvas) {
+        let now = context.clock.time();
+        let params = &self.params;
 
-    for i in 0..triangulation.triangles.len() {
-        if i > triangulation.halfedges[i] || triangulation.halfedges[i] == EMPTY {
-            let a = triangulation.triangles[i];
-            let b = triangulation.triangles[next_halfedge(i)];
-            let link = match model
-                .links
-                .iter()
-                .find(|link| a == link.a && b == link.b || a == link.b && b == link.a)
-            {
-                Some(link) => link.clone(),
-                None => Link {
-                    a: a,
-                    b: b,
-                    since: SystemTime::now(),
-                },
-            };
-            links.push(link);
+        if params.trail_half_life <= 0. {
+            canvas.rect(canvas.bounds(), params.base.background());
         }
-    }
 
-    model.links = links;
-}
+        let gradient = params.base.gradient();
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    if model.freeze {
-        return;
-    }
+        if params.triangles.filled {
+            let positions = self.particles.iter().map(|particle| particle.position).collect::<Vec<_>>();
+            let values = params.triangles.values(&positions, &self.triangulation, &self.links, now);
+            params.triangles.draw(canvas, &positions, &self.triangulation, &values, &gradient);
+        }
 
-    let draw = app.draw();
-    let win = app.window_rect();
-    if app.keys.down.contains(&Key::Delete) {
-        draw.background().color(BLACK);
-    }
+        let mean_area = self.cells.iter().map(Cell::area).sum::<f32>() / self.cells.len() as f32;
+        for cell in self.cells.iter() {
+            let particle = &self.particles[cell.site];
+            let value = match params.cells.color {
+                CellColor::Area => cell.area() / mean_area / 2.,
+                CellColor::Age => (now - particle.target_since) as f32 / (2. * params.base.particle_target_time).max(f32::EPSILON),
+                CellColor::Target => particle.position.distance(particle.target) / RADIUS,
+            };
+            let mut color = gradient.get(value.clamp(0., 1.));
+            color.alpha *= params.cells.opacity;
+            cell.draw(canvas, params.cells.style, params.cells.weight, color);
+        }
 
-    let win_p = win.pad(0.0);
-    draw.rect()
-        .xy(win_p.xy())
-        .wh(win_p.wh())
-        .color(BACKGROUND_COLOR);
-
-    let gradient = Gradient::with_domain(vec![
-        (0.0, hsla(41. / 360., 1., 0.5, 1.)),
-        (0.65, hsla(0. / 360., 1., 0.5, 1.)),
-        (1.0, hsla(234. / 360., 1., 0.5, 1.)),
-    ]);
-
-    for link in model.links.iter() {
-        let start = model.particles[link.a].position;
-        let end = model.particles[link.b].position;
-
-        let distance = start.distance(end);
-        if distance > PARTICLE_DISTANCE_MAX {
-            continue;
+        let mut color = gradient.get(params.shape.position);
+        color.alpha *= params.shape.opacity;
+        for region in self.regions.iter() {
+            region
+                .smoothed(params.shape.smoothing)
+                .draw(canvas, params.shape.style, params.shape.weight, color);
         }
-        let distance_mapped =
-            map_range::<f32, f32>(distance, PARTICLE_RADIUS, PARTICLE_DISTANCE_MAX, 0., 1.).clamp(0., 1.);
-        let distance_mapped_eased = 1. - cubic::ease_out(distance_mapped, 0.01, 1., 1.);
 
-        let since = link.since.elapsed().unwrap().as_secs_f32();
-        let since_mapped: f32 = map_range::<f32, f32>(since, 0.0, 2.7, 1., 0.).clamp(0., 1.);
-        let since_mapped_eased = 1. - cubic::ease_out(since_mapped, 0., 1., 1.);
+        for link in self.links.iter() {
+            let start = self.particles[link.a].position;
+            let end = self.particles[link.b].position;
 
+            let distance = start.distance(end);
+            if distance > params.base.particle_distance_max {
+                continue;
+            }
+            let distance_mapped =
+                map_range::<f32, f32>(distance, params.base.particle_radius, params.base.particle_distance_max, 0., 1.).clamp(0., 1.);
+            let distance_mapped_eased = 1. - cubic::ease_out(distance_mapped, 0.01, 1., 1.);
 
-        let mut color = gradient.get(1.-(distance_mapped*1.5 - 0.3).clamp(0., 1.));
-        color.alpha = ((distance_mapped_eased/2.) * (since_mapped_eased*2.)).clamp(0., 0.6);
+            let since_mapped_eased = link.fade_in(now, 2.7) * self.links.fade_out(link, now);
 
-        draw.line()
-            .color(color)
-            .weight(LINE_WIGHT)
-            .caps_round()
-            .points(start, end);
-    }
 
-    let elapsed_time = app.elapsed_frames() as f32 / 3400.0;
+            let mut color = gradient.get(1.-(distance_mapped*1.5 - 0.3).clamp(0., 1.));
+            color.alpha = ((distance_mapped_eased/2.) * (since_mapped_eased*2.)).clamp(0., 0.6);
+
+            canvas.line(start, end, params.base.line_weight, color);
+        }
+
+        let elapsed_time = context.clock.steps() as f32 / 3400.0;
 
-    for i in 0..9 {
-        let radius = (elapsed_time + i as f32 * 0.5).sin() * 1700.0 + 50.0;
-        draw.ellipse()
-            .x_y(0.0, 0.0)
-            .radius(radius)
-            .no_fill()
-            .stroke(color::GHOSTWHITE)
-            .stroke_weight(1.0);
+        for i in 0..9 {
+            let radius = (elapsed_time + i as f32 * 0.5).sin() * 1700.0 + 50.0;
+            canvas.ellipse_outline(vec2(0.0, 0.0), radius, 1.0, color::GHOSTWHITE);
+        }
     }
 
-    draw.to_frame(app, &frame).unwrap();
+    fn event(&mut self, app: &App, context: &mut Context, event: &Event) -> bool {
+        match event {
+            Event::Frame(elapsed) => {
+                if let Some(params) = self.params_file.poll() {
+                    self.apply_params(context, params);
+                }
+
+                if let Some(params) = self.presets.update(*elapsed) {
+                    self.apply_params(context, params);
+                }
+                context.controls.status = self.presets.status();
+
+                if context.controls.panel.visible {
+                    let mut params = self.params.clone();
+                    if params.edit(&mut context.controls.panel.ui(app)) {
+                        self.apply_params(context, params);
+                    }
+                }
+
+                let steps = context.clock.steps();
+                if let Some(mut history) = self.plot_history.take() {
+                    if !history.full() && steps >= self.plot_history_step + HISTORY_STEPS {
+                        self.draw(context, &mut history);
+                        self.plot_history_step = steps;
+                        if history.full() {
+                            println!("plot history full, H saves it");
+                        }
+                    }
+                    self.plot_history = Some(history);
+                }
+            }
+            Event::Window(ReceivedCharacter(character)) => {
+                return self.presets.received_character(*character);
+            }
+            Event::Window(KeyReleased(key)) => {
+                if self.presets.key_released(app, *key, &self.params) {
+                    return true;
+                }
+                self.key_released(app, context, *key);
+            }
+            _ => (),
+        }
+        false
+    }
 }
 
-fn key_released(app: &App, model: &mut Model, key: Key) {
-    match key {
-        Key::S => {
-            let now = SystemTime::now();
-            app.main_window().capture_frame(
-                "out/".to_owned()
-                    + &app.exe_name().unwrap()
-                    + "#"
-                    + &now
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis()
-                        .to_string()
-                    + ".png",
-            );
+impl Model {
+    fn with_params(context: &mut Context, params: Settings, params_file: ParamsFile<Settings>) -> Self {
+        let mut rng = seeded(context.controls.seed);
+        let presets = Presets::for_sketch(file!(), &default_params());
+        context.controls.set_params(&params);
+
+        let particles = (0..params.base.particle_number.max(2))
+            .map(|_| new_particle(&mut rng, &params.base, context.clock.time()))
+            .collect::<Vec<Particle>>();
+        let triangulation = triangulate_particles(&particles);
+
+        Model {
+            rng,
+            particles,
+            links: LinkTracker::new(LINK_FADE_OUT),
+            cells: vec![],
+            regions: vec![],
+            triangulation,
+            plot_history: None,
+            plot_history_step: 0,
+            params,
+            params_file,
+            presets,
+        }
+    }
+
+    /// Applies changed parameters to the running simulation. Particles are
+    /// only added or removed at the end, the others keep going.
+    fn apply_params(&mut self, context: &mut Context, params: Settings) {
+        let now = context.clock.time();
+        let count = params.base.particle_number.max(2);
+        self.particles.truncate(count);
+        while self.particles.len() < count {
+            let particle = new_particle(&mut self.rng, &params.base, now);
+            self.particles.push(particle);
         }
-        Key::F => {
-            model.freeze = !model.freeze;
+        for particle in self.particles.iter_mut() {
+            particle.radius = params.base.particle_radius;
+        }
+        self.links.retain(|link| link.a < count && link.b < count);
+        self.cells.retain(|cell| cell.site < count);
+        self.triangulation = triangulate_particles(&self.particles);
+        context.controls.set_params(&params);
+        self.params = params;
+    }
+
+    fn key_released(&mut self, app: &App, context: &mut Context, key: Key) {
+        let seed = context.controls.seed;
+        match key {
+            Key::V => {
+                capture::capture_svg(app, seed, |svg| self.draw(context, svg));
+            }
+            Key::P => {
+                capture::capture_plot(app, seed, &plot_settings(&self.params.base), |plot| {
+                    self.draw(context, plot)
+                });
+            }
+            Key::A | Key::C | Key::T | Key::G => {
+                let mut params = self.params.clone();
+                match key {
+                    Key::A => params.shape.style = params.shape.style.next(),
+                    Key::C => params.cells.style = params.cells.style.next(),
+                    Key::T => params.triangles.filled = !params.triangles.filled,
+                    _ => params.graph.graph = params.graph.graph.next(),
+                }
+                self.apply_params(context, params);
+            }
+            Key::H => match self.plot_history.take() {
+                Some(history) => {
+                    capture::save_plot(app, seed, &plot_settings(&self.params.base), &history);
+                }
+                None => {
+                    self.plot_history = Some(Plot::with_limit(app.window_rect(), HISTORY_STROKES));
+                    self.plot_history_step = 0;
+                }
+            },
+            _ => (),
         }
-        _ => (),
     }
 }
 
-fn random_point_in_radius(o: &Vec2, r: f32) -> Vec2 {
-    let r = r * random_f32().sqrt();
-    let t = random_f32() * 2.0 * PI;
-    vec2(o.x + r * t.cos(), o.y + r * t.sin())
+fn new_particle(rng: &mut StdRng, params: &Params, now: f64) -> Particle {
+    Particle::new(
+        random_point_in_radius(rng, &ORIGIN, RADIUS * 2.),
+        params.particle_radius,
+        random_point_in_radius(rng, &ORIGIN, RADIUS / 2.),
+        now,
+    )
+}
+
+fn plot_settings(params: &Params) -> PlotSettings {
+    PlotSettings {
+        pens: params
+            .gradient_stops()
+            .into_iter()
+            .map(|(_, color)| color.into_lin_srgba())
+            .collect(),
+        ..Default::default()
+    }
 }
@@ -0,0 +1,38 @@
+use rustyart::gallery::Gallery;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+/// Builds `out/gallery/index.html` from the captures in `out/`, rendering
+/// previews of sketches without captures after `--warmup <steps>`, 1200 by
+/// default, unless `--no-render`.
+fn main() {
+    let mut gallery = Gallery {
+        out: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("out"),
+        warmup: 1200,
+        render: true,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-render" => gallery.render = false,
+            "--warmup" => match args.next().and_then(|steps| steps.parse().ok()) {
+                Some(steps) => gallery.warmup = steps,
+                None => usage(),
+            },
+            _ => usage(),
+        }
+    }
+    match gallery.build() {
+        Ok(path) => println!("wrote {}", path.display()),
+        Err(error) => {
+            eprintln!("gallery: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: gallery [--no-render] [--warmup <steps>]");
+    process::exit(2);
+}
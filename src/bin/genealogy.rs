@@ -0,0 +1,41 @@
+use rustyart::genealogy::Genealogy;
+use std::env;
+use std::fs;
+use std::process;
+
+/// Prints which sketch each one was most likely copied from and what
+/// changed, and which sketches are exact copies. `--dot <path>` and
+/// `--html <path>` export the tree.
+fn main() {
+    let genealogy = Genealogy::load().unwrap_or_else(|error| {
+        eprintln!("genealogy: {}", error);
+        process::exit(1);
+    });
+    let mut args = env::args().skip(1);
+    let mut exported = false;
+    while let Some(arg) = args.next() {
+        let (text, path) = match (arg.as_str(), args.next()) {
+            ("--dot", Some(path)) => (genealogy.to_dot(), path),
+            ("--html", Some(path)) => (genealogy.to_html(), path),
+            _ => {
+                eprintln!("usage: genealogy [--dot <path>] [--html <path>]");
+                process::exit(2);
+            }
+        };
+        fs::write(&path, text).expect("failed to write genealogy");
+        println!("wrote {}", path);
+        exported = true;
+    }
+    if exported {
+        return;
+    }
+
+    print!("{}", genealogy.to_text());
+    let duplicates = genealogy.duplicates();
+    if !duplicates.is_empty() {
+        println!("duplicates");
+        for group in duplicates {
+            println!("    {}", group.join(" = "));
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use rustyart::sketches::{self, Lineage};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+const HELP: &str = "usage: luna <command>
+commands:
+    run | r [sketch] [args]   run a sketch, the newest by default
+    new | n                   start an empty sketch
+    copy | c [sketch]         copy a sketch, the newest by default
+    save | s                  commit everything and push
+    savecopy | sc [sketch]    save, then copy
+    list | l [query]          list sketches with their parents
+    help
+sketches are names, -n for the nth newest, n for the nth oldest, or
+anything fuzzy matching a name like 1203 or 12-03-14";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let rest = args.collect::<Vec<String>>();
+    let selector = rest.first().map(|s| s.as_str());
+    let result = match command.as_str() {
+        "run" | "r" => run(selector, rest.iter().skip(1)),
+        "new" | "n" => new(),
+        "copy" | "c" => copy(selector),
+        "save" | "s" => save(),
+        "savecopy" | "sc" => save().and_then(|_| copy(selector)),
+        "list" | "l" => list(selector),
+        _ => {
+            println!("{}", HELP);
+            Ok(())
+        }
+    };
+    if let Err(error) = result {
+        eprintln!("luna: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run<'a>(selector: Option<&str>, args: impl Iterator<Item = &'a String>) -> Result<(), String> {
+    let sketches = sketches::list();
+    let name = sketches::find(&sketches, selector)?;
+    println!("{}", name);
+    let status = Command::new("cargo")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("GDK_BACKEND", "x11")
+        .args(["run", "--release", "--bin", "sketchbook", "--", name])
+        .args(args)
+        .status()
+        .map_err(|error| error.to_string())?;
+    process::exit(status.code().unwrap_or(1));
+}
+
+fn new() -> Result<(), String> {
+    let name = timestamp()?;
+    let path = sketches::source(&name);
+    fs::write(&path, "").map_err(|error| error.to_string())?;
+    println!("{}", name);
+    open(&path)
+}
+
+/// Copies the sketch with its parameter and preset files, and records
+/// where the copy came from.
+fn copy(selector: Option<&str>) -> Result<(), String> {
+    let mut lineage = Lineage::load()?;
+    let sketches = sketches::list();
+    let parent = sketches::find(&sketches, selector)?;
+    let name = timestamp()?;
+    if sketches.contains(&name) {
+        return Err(format!("{} already exists", name));
+    }
+    let dir = sketches::dir();
+    for extension in ["rs", "toml", "presets.toml"] {
+        let from = dir.join(format!("{}.{}", parent, extension));
+        if from.exists() {
+            let to = dir.join(format!("{}.{}", name, extension));
+            fs::copy(from, to).map_err(|error| error.to_string())?;
+        }
+    }
+    lineage.parents.insert(name.clone(), parent.to_owned());
+    lineage.save().map_err(|error| error.to_string())?;
+    println!("{} <- {}", name, parent);
+    open(&sketches::source(&name))
+}
+
+fn save() -> Result<(), String> {
+    git(&["add", "--all"])?;
+    let message = commit_message()?;
+    println!("{}", message);
+    git(&["commit", "--message", &message])?;
+    git(&["push"])
+}
+
+fn list(query: Option<&str>) -> Result<(), String> {
+    let sketches = sketches::list();
+    let lineage = Lineage::load()?;
+    for (i, sketch) in sketches.iter().enumerate() {
+        if query.is_some_and(|query| sketches::fuzzy_score(query, sketch).is_none()) {
+            continue;
+        }
+        let back = sketches.len() - i;
+        match lineage.parent(sketch) {
+            Some(parent) => println!(
+                "{:>4} {:>5}  {} <- {}",
+                i + 1,
+                -(back as i64),
+                sketch,
+                parent
+            ),
+            None => println!("{:>4} {:>5}  {}", i + 1, -(back as i64), sketch),
+        }
+    }
+    Ok(())
+}
+
+/// One line per staged change. New sketches say what they were copied from
+/// and which constants differ from the parent, changed sketches which
+/// constants changed, parameter files which values.
+fn commit_message() -> Result<String, String> {
+    let lineage = Lineage::load()?;
+    let status = git_output(&["diff", "--cached", "--name-status"])?;
+    let mut entries = status.lines().collect::<Vec<&str>>();
+    entries.sort_by_key(|entry| !entry.ends_with(".rs"));
+    let mut lines = vec![];
+    let mut other = vec![];
+    for entry in entries {
+        let mut parts = entry.split('\t');
+        let (status, path) = match (parts.next(), parts.next_back()) {
+            (Some(status), Some(path)) => (status, path),
+            _ => continue,
+        };
+        let file = Path::new(path);
+        let stem = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .unwrap_or_default();
+        let in_bin = file.parent() == Some(Path::new("src/bin"));
+        if !in_bin || !sketches::is_sketch(stem) {
+            other.push(path.to_owned());
+            continue;
+        }
+        let old = match status {
+            "A" => lineage.parent(stem).and_then(|parent| {
+                let parent_path = path.replacen(stem, parent, 1);
+                git_output(&["show", &format!(":{}", parent_path)]).ok()
+            }),
+            "D" => {
+                lines.push(format!("remove {}", path));
+                continue;
+            }
+            _ => git_output(&["show", &format!("HEAD:{}", path)]).ok(),
+        };
+        let new = git_output(&["show", &format!(":{}", path)]).unwrap_or_default();
+        let changes = if path.ends_with(".rs") {
+            sketches::diff_constants(
+                &sketches::constants(old.as_deref().unwrap_or_default()),
+                &sketches::constants(&new),
+            )
+        } else {
+            let parse = |text: &str| {
+                text.parse::<toml::Value>()
+                    .map(|value| sketches::flatten_toml(&value))
+                    .unwrap_or_default()
+            };
+            sketches::diff_constants(&parse(old.as_deref().unwrap_or_default()), &parse(&new))
+        };
+        let kind = match &path[path.find('.').unwrap_or(0)..] {
+            ".rs" => "",
+            ".presets.toml" => " presets",
+            ".toml" => " params",
+            _ => " other",
+        };
+        let parent = lineage.parent(stem);
+        let what = match (status, kind, parent) {
+            ("A", "", Some(parent)) => format!("new sketch {} from {}", stem, parent),
+            ("A", "", None) => format!("new sketch {}", stem),
+            _ => format!("{}{}", stem, kind),
+        };
+        match (status, changes.is_empty(), parent) {
+            // Copied along with the sketch and left as they were.
+            ("A", true, Some(_)) if !kind.is_empty() => (),
+            ("A", true, _) => lines.push(what),
+            (_, true, _) => lines.push(format!("{}: edit", what)),
+            (_, false, _) => lines.push(format!("{}: {}", what, changes.join(", "))),
+        }
+    }
+    if !other.is_empty() {
+        lines.push(format!("update {}", other.join(", ")));
+    }
+    match lines.len() {
+        0 => Err("nothing to save".to_owned()),
+        1 => Ok(lines.remove(0)),
+        _ => Ok(format!(
+            "{} and {} more\n\n{}",
+            lines[0].split(':').next().unwrap(),
+            lines.len() - 1,
+            lines.join("\n")
+        )),
+    }
+}
+
+fn timestamp() -> Result<String, String> {
+    let output = Command::new("date")
+        .arg("+%F-%H-%M-%S")
+        .output()
+        .map_err(|error| error.to_string())?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// In `$LUNA_EDITOR`, VS Code by default.
+fn open(path: &Path) -> Result<(), String> {
+    let editor = env::var("LUNA_EDITOR").unwrap_or_else(|_| "code".to_owned());
+    Command::new(editor)
+        .arg(path)
+        .status()
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+fn git(args: &[&str]) -> Result<(), String> {
+    let status = Command::new("git")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .status()
+        .map_err(|error| error.to_string())?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("git {} failed", args.join(" "))),
+    }
+}
+
+fn git_output(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .output()
+        .map_err(|error| error.to_string())?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+    }
+}
@@ -0,0 +1,64 @@
+use rustyart::metadata::{self, Metadata};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+/// Relaunches the sketch that captured a PNG with the seed, parameters and
+/// step embedded in it. Further arguments go to the sketch, so
+/// `reproduce <png> --poster big.png` renders it large instead.
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = PathBuf::from(args.next().unwrap_or_else(|| {
+        eprintln!("usage: reproduce <png> [sketch arguments]");
+        process::exit(2);
+    }));
+    let metadata = match Metadata::read(&path) {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => {
+            eprintln!("{} was not captured by a sketch", path.display());
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let revision = metadata::revision();
+    if revision != metadata.revision {
+        eprintln!(
+            "captured at {}, the tree is at {}, the result may differ",
+            metadata.revision, revision
+        );
+    }
+
+    let mut command = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()));
+    command
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([
+            "run",
+            "--release",
+            "--bin",
+            "sketchbook",
+            "--",
+            &metadata.sketch,
+        ])
+        .args(["--seed", &metadata.seed.to_string()])
+        .args(["--steps", &metadata.step.to_string()]);
+    if let Some(params) = &metadata.params {
+        let params_path = path.with_extension("params.toml");
+        fs::write(&params_path, params).expect("failed to write params");
+        command
+            .arg("--params")
+            .arg(fs::canonicalize(&params_path).unwrap());
+    }
+    command.args(args);
+
+    println!(
+        "{} seed {} step {}",
+        metadata.sketch, metadata.seed, metadata.step
+    );
+    let status = command.status().expect("failed to run cargo");
+    process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,96 @@
+use rustyart::registry::{self, Entry};
+use rustyart::sketches;
+use std::env;
+use std::process;
+
+include!(concat!(env!("OUT_DIR"), "/sketches.rs"));
+
+const HELP: &str = "usage: sketchbook [sketch] [sketch arguments]
+       sketchbook list [query]
+       sketchbook renderable
+       sketchbook completions bash|zsh
+sketches are names, -n for the nth newest, n for the nth oldest, or
+anything fuzzy matching a name like 1203 or 12-03-14, the newest by default";
+
+/// Every sketch in one binary, picked at runtime.
+fn main() {
+    let mut args = env::args().skip(1);
+    let first = args.next();
+    let names = SKETCHES
+        .iter()
+        .map(|sketch| sketch.name.to_owned())
+        .collect::<Vec<String>>();
+    match first.as_deref() {
+        Some("help" | "--help" | "-h") => println!("{}", HELP),
+        Some("list") => list(&names, args.next().as_deref()),
+        Some("renderable") => {
+            for sketch in SKETCHES.iter().filter(|sketch| sketch.renders) {
+                println!("{}", sketch.name);
+            }
+        }
+        Some("completions") => match args.next().as_deref() {
+            Some("bash") => print!("{}", completions(&names)),
+            Some("zsh") => print!(
+                "autoload -U bashcompinit\nbashcompinit\n{}",
+                completions(&names)
+            ),
+            _ => usage(),
+        },
+        selector => match sketches::find(&names, selector) {
+            Ok(name) => {
+                let sketch = SKETCHES.iter().find(|sketch| sketch.name == name).unwrap();
+                let args = args.collect::<Vec<String>>();
+                let offline = ["--render", "--video", "--poster"];
+                if !sketch.renders && args.iter().any(|arg| offline.contains(&arg.as_str())) {
+                    eprintln!("sketchbook: {} only runs in a window", name);
+                    process::exit(1);
+                }
+                eprintln!("{}", name);
+                registry::run(sketch, args);
+            }
+            Err(error) => {
+                eprintln!("sketchbook: {}", error);
+                process::exit(1);
+            }
+        },
+    }
+}
+
+fn list(names: &[String], query: Option<&str>) {
+    for (i, name) in names.iter().enumerate() {
+        if query.is_some_and(|query| sketches::fuzzy_score(query, name).is_none()) {
+            continue;
+        }
+        println!("{:>4} {:>5}  {}", i + 1, -((names.len() - i) as i64), name);
+    }
+}
+
+/// Completes sketch names for `sketchbook` and for `luna run` and `copy`,
+/// to be sourced from the shell's startup file.
+fn completions(names: &[String]) -> String {
+    format!(
+        r#"_rustyart() {{
+    local sketches="{}"
+    local word="${{COMP_WORDS[COMP_CWORD]}}"
+    case "${{COMP_WORDS[0]##*/}}:$COMP_CWORD" in
+        sketchbook:1)
+            COMPREPLY=($(compgen -W "list renderable completions help $sketches" -- "$word"));;
+        luna:1)
+            COMPREPLY=($(compgen -W "run new copy save savecopy list help" -- "$word"));;
+        luna:2)
+            case "${{COMP_WORDS[1]}}" in
+                r|run|c|copy|sc|savecopy)
+                    COMPREPLY=($(compgen -W "$sketches" -- "$word"));;
+            esac;;
+    esac
+}}
+complete -F _rustyart sketchbook luna
+"#,
+        names.join(" ")
+    )
+}
+
+fn usage() -> ! {
+    eprintln!("{}", HELP);
+    process::exit(2);
+}
@@ -0,0 +1,90 @@
+use crate::metadata::Metadata;
+use crate::plot::{self, Plot, PlotSettings};
+use crate::registry;
+use crate::svg::Svg;
+use nannou::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `out/<sketch>#<millis>.png`
+pub fn capture_path(app: &App) -> String {
+    let now = SystemTime::now();
+    "out/".to_owned()
+        + &registry::name(app)
+        + "#"
+        + &now
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+            .to_string()
+        + ".png"
+}
+
+/// Captures the next frame with `metadata` embedded, and writes the seed
+/// next to it as `out/<sketch>#<millis>.seed`.
+pub fn capture(app: &App, metadata: Metadata) {
+    let path = capture_path(app);
+    app.main_window().capture_frame(&path);
+    write_seed(Path::new(&path).with_extension("seed"), metadata.seed);
+    metadata.embed_when_written(path.into());
+}
+
+/// Exports what `draw` draws on the window as `out/<sketch>#<millis>.svg`,
+/// with the seed next to it like `capture`.
+pub fn capture_svg(app: &App, seed: u64, draw: impl FnOnce(&mut Svg)) {
+    let path = Path::new(&capture_path(app)).with_extension("svg");
+    let mut svg = Svg::new(app.window_rect());
+    draw(&mut svg);
+    svg.save(&path);
+    write_seed(path.with_extension("seed"), seed);
+}
+
+/// Plots the lines `draw` draws on the window, see `save_plot`.
+pub fn capture_plot(app: &App, seed: u64, settings: &PlotSettings, draw: impl FnOnce(&mut Plot)) {
+    let mut plot = Plot::new(app.window_rect());
+    draw(&mut plot);
+    save_plot(app, seed, settings, &plot);
+}
+
+/// Writes `out/<sketch>#<millis>.hpgl` and `.gcode` for a recorded plot, with
+/// the seed next to them like `capture`.
+pub fn save_plot(app: &App, seed: u64, settings: &PlotSettings, plot: &Plot) {
+    let path = Path::new(&capture_path(app)).with_extension("hpgl");
+    let layers = plot::layers(plot, settings);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).expect("failed to create capture directory");
+    }
+    fs::write(&path, plot::hpgl(&layers)).expect("failed to write hpgl");
+    fs::write(path.with_extension("gcode"), plot::gcode(&layers, settings))
+        .expect("failed to write gcode");
+    write_seed(path.with_extension("seed"), seed);
+    println!(
+        "plotted {} paths on {} pens, {:.0}mm pen-up travel",
+        layers.iter().map(|layer| layer.paths.len()).sum::<usize>(),
+        layers.len(),
+        layers.iter().map(|layer| layer.travel()).sum::<f32>(),
+    );
+}
+
+fn write_seed(path: impl AsRef<Path>, seed: u64) {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).expect("failed to create capture directory");
+    }
+    fs::write(path, seed.to_string() + "\n").expect("failed to write capture seed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_seed_creates_the_directory() {
+        let dir = std::env::temp_dir().join(format!("rustyart-capture-{}", std::process::id()));
+        let path = dir.join("out/sketch#1.seed");
+        write_seed(&path, 42);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "42\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
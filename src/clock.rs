@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+/// Simulated time, advanced from the real time between updates. With a fixed
+/// step every step has the same length, so a run only depends on the number
+/// of steps and not on the frame rate.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    pub paused: bool,
+    pub scale: f32,
+    pub fixed_step: Option<f32>,
+    pub max_steps: u32,
+    time: f64,
+    steps: u64,
+    step: f32,
+    accumulator: f64,
+    requested: u32,
+    stepped: u32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::fixed(1. / 60.)
+    }
+}
+
+impl Clock {
+    pub fn fixed(step: f32) -> Self {
+        Clock {
+            paused: false,
+            scale: 1.,
+            fixed_step: Some(step),
+            max_steps: 8,
+            time: 0.,
+            steps: 0,
+            step,
+            accumulator: 0.,
+            requested: 0,
+            stepped: 0,
+        }
+    }
+
+    /// One step per update, as long as the real time since the last one.
+    pub fn variable() -> Self {
+        Clock {
+            fixed_step: None,
+            ..Self::fixed(0.)
+        }
+    }
+
+    /// Simulated seconds. In `f64`, so timestamps taken from it keep their
+    /// precision in long sessions.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Length of the current step in simulated seconds.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Runs exactly one step on the next update, also while paused.
+    pub fn single_step(&mut self) {
+        self.requested += 1;
+    }
+
+    /// Paused and the last `advance` produced no steps, so there is nothing
+    /// new to draw.
+    pub fn frozen(&self) -> bool {
+        self.paused && self.stepped == 0
+    }
+
+    /// Number of steps to run for `elapsed` real time in a window. Call
+    /// `tick` once before each of them. Time beyond `max_steps` steps is
+    /// dropped, so a window that can't keep up slows down instead of falling
+    /// ever further behind.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        let steps = self.steps_for(elapsed, f64::floor);
+        self.stepped = steps.min(self.max_steps);
+        if steps > self.max_steps {
+            self.accumulator = 0.;
+        }
+        self.stepped
+    }
+
+    /// Like `advance` for runs off the window, which must not lose time: no
+    /// limit, and `elapsed` rounded to the nearest step with the remainder
+    /// carried over, so rounding errors in it can't add or skip steps.
+    pub fn advance_exact(&mut self, elapsed: Duration) -> u32 {
+        self.stepped = self.steps_for(elapsed, f64::round);
+        self.stepped
+    }
+
+    /// Steps for `elapsed` with fixed steps counted by `whole`.
+    fn steps_for(&mut self, elapsed: Duration, whole: fn(f64) -> f64) -> u32 {
+        let elapsed = elapsed.as_secs_f64() * self.scale as f64;
+        let steps = match (self.paused, self.fixed_step) {
+            (true, Some(step)) => {
+                self.step = step;
+                self.requested
+            }
+            (true, None) => {
+                self.step = 1. / 60.;
+                self.requested
+            }
+            (false, Some(step)) => {
+                self.step = step;
+                self.accumulator += elapsed;
+                let steps = whole(self.accumulator / step as f64).max(0.);
+                self.accumulator -= steps * step as f64;
+                steps as u32 + self.requested
+            }
+            (false, None) => {
+                self.step = elapsed as f32;
+                1 + self.requested
+            }
+        };
+        self.requested = 0;
+        steps
+    }
+
+    pub fn tick(&mut self) {
+        self.time += self.step as f64;
+        self.steps += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(clock: &mut Clock, steps: u32) {
+        for _ in 0..steps {
+            clock.tick();
+        }
+    }
+
+    #[test]
+    fn fixed_steps_carry_the_remainder() {
+        let mut clock = Clock::fixed(0.25);
+        assert_eq!(clock.advance(Duration::from_millis(625)), 2);
+        run(&mut clock, 2);
+        assert_eq!(clock.advance(Duration::from_millis(125)), 1);
+        run(&mut clock, 1);
+        assert_eq!(clock.steps(), 3);
+        assert_eq!(clock.time(), 0.75);
+    }
+
+    #[test]
+    fn advance_drops_time_beyond_max_steps() {
+        let mut clock = Clock::fixed(0.25);
+        clock.max_steps = 4;
+        assert_eq!(clock.advance(Duration::from_secs(10)), 4);
+        assert_eq!(clock.advance(Duration::from_millis(125)), 0);
+    }
+
+    #[test]
+    fn advance_exact_neither_adds_nor_skips_steps() {
+        let mut clock = Clock::fixed(1. / 60.);
+        // A frame of 1/60 s as a `Duration` is slightly short of a step.
+        let frame = Duration::from_secs_f32(1. / 60.);
+        let steps = (0..6000).map(|_| clock.advance_exact(frame)).sum::<u32>();
+        assert_eq!(steps, 6000);
+        assert_eq!(
+            Clock::fixed(0.1).advance_exact(Duration::from_secs(100)),
+            1000
+        );
+    }
+
+    #[test]
+    fn paused_clock_only_runs_requested_steps() {
+        let mut clock = Clock::fixed(0.1);
+        clock.toggle_pause();
+        assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+        assert!(clock.frozen());
+        clock.single_step();
+        assert_eq!(clock.advance(Duration::from_secs(1)), 1);
+        assert!(!clock.frozen());
+        assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn scale_speeds_up_time() {
+        let mut clock = Clock::fixed(0.25);
+        clock.scale = 2.;
+        assert_eq!(clock.advance(Duration::from_millis(500)), 4);
+    }
+
+    #[test]
+    fn variable_clock_steps_once_per_update() {
+        let mut clock = Clock::variable();
+        assert_eq!(clock.advance(Duration::from_millis(40)), 1);
+        assert!((clock.step() - 0.04).abs() < 1e-6);
+    }
+}
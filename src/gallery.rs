@@ -0,0 +1,300 @@
+use crate::metadata::Metadata;
+use crate::sketches::{self, Lineage};
+use nannou::image;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const THUMBNAIL: (u32, u32) = (480, 270);
+
+/// A PNG saved by a sketch, `out/<sketch>#<millis>.png`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capture {
+    pub path: PathBuf,
+    pub sketch: String,
+    /// When it was captured, in milliseconds since the epoch.
+    pub millis: u64,
+    pub metadata: Option<Metadata>,
+}
+
+impl Capture {
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let (sketch, millis) = stem.split_once('#')?;
+        if path.extension()? != "png" {
+            return None;
+        }
+        Some(Capture {
+            sketch: sketch.to_owned(),
+            millis: millis.parse().ok()?,
+            metadata: Metadata::read(&path).ok().flatten(),
+            path,
+        })
+    }
+
+    /// The file name, `#` escaped for use in links.
+    pub fn href(&self) -> String {
+        let name = self.path.file_name().unwrap().to_string_lossy();
+        name.replace('#', "%23")
+    }
+
+    pub fn thumbnail_name(&self) -> String {
+        format!("{}-{}.png", self.sketch, self.millis)
+    }
+}
+
+/// Every capture in `dir`, grouped by sketch, newest first.
+pub fn captures(dir: &Path) -> io::Result<BTreeMap<String, Vec<Capture>>> {
+    let mut captures: BTreeMap<String, Vec<Capture>> = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        if let Some(capture) = Capture::from_path(entry?.path()) {
+            captures
+                .entry(capture.sketch.clone())
+                .or_default()
+                .push(capture);
+        }
+    }
+    for group in captures.values_mut() {
+        group.sort_by_key(|capture| std::cmp::Reverse(capture.millis));
+    }
+    Ok(captures)
+}
+
+/// A static page of every sketch with its captures, written to
+/// `<out>/gallery/index.html` with thumbnails next to it.
+#[derive(Clone, Debug)]
+pub struct Gallery {
+    pub out: PathBuf,
+    /// Steps to simulate before rendering a preview of a sketch without
+    /// captures, so it has settled.
+    pub warmup: u64,
+    /// Render previews of sketches without captures, slow as each of them
+    /// is built and run.
+    pub render: bool,
+}
+
+impl Gallery {
+    pub fn dir(&self) -> PathBuf {
+        self.out.join("gallery")
+    }
+
+    pub fn thumbnails(&self) -> PathBuf {
+        self.dir().join("thumbnails")
+    }
+
+    /// Builds the page, making the thumbnails that are missing or older
+    /// than their capture.
+    pub fn build(&self) -> io::Result<PathBuf> {
+        fs::create_dir_all(self.thumbnails())?;
+        let captures = captures(&self.out)?;
+        let lineage =
+            Lineage::load().map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let renderable = self.renderable();
+
+        let mut html = String::from(HTML_HEAD);
+        for sketch in sketches::list().iter().rev() {
+            let group = captures.get(sketch).map_or(&[][..], |group| &group[..]);
+            writeln!(html, "<section id=\"{0}\">\n<h2>{0}</h2>", sketch).unwrap();
+            write!(
+                html,
+                "<p><a href=\"../../src/bin/{0}.rs\">source</a>",
+                sketch
+            )
+            .unwrap();
+            if let Some(parent) = lineage.parent(sketch) {
+                write!(html, " from <a href=\"#{0}\">{0}</a>", parent).unwrap();
+            }
+            html.push_str("</p>\n");
+
+            for capture in group {
+                let thumbnail = self.thumbnails().join(capture.thumbnail_name());
+                if let Err(error) = thumbnail_of(&capture.path, &thumbnail) {
+                    eprintln!("{}: {}", capture.path.display(), error);
+                    continue;
+                }
+                write_figure(&mut html, capture);
+            }
+            if group.is_empty() {
+                match self.preview(sketch, &renderable) {
+                    Some(name) => writeln!(
+                        html,
+                        "<figure><img src=\"thumbnails/{}\"><figcaption>preview, seed 1</figcaption></figure>",
+                        name
+                    )
+                    .unwrap(),
+                    None => html.push_str("<p class=\"empty\">no captures</p>\n"),
+                }
+            }
+            html.push_str("</section>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+
+        let path = self.dir().join("index.html");
+        fs::write(&path, html)?;
+        Ok(path)
+    }
+
+    /// The sketches that can render without a window, as the `sketchbook`
+    /// registered them. None unless previews are rendered.
+    fn renderable(&self) -> Vec<String> {
+        if !self.render {
+            return vec![];
+        }
+        match sketchbook().arg("renderable").output() {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.to_owned())
+                .collect(),
+            _ => {
+                eprintln!("failed to list renderable sketches, rendering no previews");
+                vec![]
+            }
+        }
+    }
+
+    /// The thumbnail of a sketch rendered headlessly, for sketches in
+    /// `renderable`. Kept once rendered.
+    fn preview(&self, sketch: &str, renderable: &[String]) -> Option<String> {
+        let name = format!("{}.png", sketch);
+        let thumbnail = self.thumbnails().join(&name);
+        if thumbnail.exists() {
+            return Some(name);
+        }
+        if !renderable.iter().any(|name| name == sketch) {
+            return None;
+        }
+
+        eprintln!("rendering {}", sketch);
+        let render = fs::canonicalize(self.thumbnails())
+            .ok()?
+            .join(format!("{}.full.png", sketch));
+        let status = sketchbook()
+            .arg(sketch)
+            .args(["--seed", "1", "--steps", &self.warmup.to_string()])
+            .args(["--frames", "60", "--render"])
+            .arg(&render)
+            .status()
+            .ok()?;
+        let result = match status.success() {
+            true => thumbnail_of(&render, &thumbnail).ok(),
+            false => None,
+        };
+        fs::remove_file(render).ok();
+        result.map(|_| name)
+    }
+}
+
+/// `cargo run` of the release `sketchbook`, waiting for its arguments.
+fn sketchbook() -> Command {
+    let mut command = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()));
+    command.current_dir(env!("CARGO_MANIFEST_DIR")).args([
+        "run",
+        "--release",
+        "--quiet",
+        "--bin",
+        "sketchbook",
+        "--",
+    ]);
+    command
+}
+
+/// Scales `source` down to `thumbnail` unless that is newer.
+fn thumbnail_of(source: &Path, thumbnail: &Path) -> io::Result<()> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    if let (Ok(source), Ok(thumbnail)) = (modified(source), modified(thumbnail)) {
+        if thumbnail >= source {
+            return Ok(());
+        }
+    }
+    let image = image::open(source).map_err(io::Error::other)?;
+    image
+        .thumbnail(THUMBNAIL.0, THUMBNAIL.1)
+        .save(thumbnail)
+        .map_err(io::Error::other)
+}
+
+fn write_figure(html: &mut String, capture: &Capture) {
+    write!(
+        html,
+        "<figure><a href=\"../{}\"><img src=\"thumbnails/{}\" loading=\"lazy\"></a><figcaption>{}",
+        capture.href(),
+        capture.thumbnail_name(),
+        date(capture.millis)
+    )
+    .unwrap();
+    if let Some(metadata) = &capture.metadata {
+        write!(
+            html,
+            "<br>seed {} step {} at {}",
+            metadata.seed,
+            metadata.step,
+            escape(&metadata.revision)
+        )
+        .unwrap();
+        if let Some(params) = &metadata.params {
+            write!(
+                html,
+                "<details><summary>params</summary><pre>{}</pre></details>",
+                escape(params)
+            )
+            .unwrap();
+        }
+    }
+    html.push_str("</figcaption></figure>\n");
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+fn date(millis: u64) -> String {
+    let seconds = (millis / 1000) as i64;
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // The civil calendar from days since the epoch, years starting in March.
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_HEAD: &str = "<!doctype html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>rustyart</title>
+<style>
+body { background: #181818; color: #ddd; font: 14px monospace; margin: 2em; }
+a { color: #8bf; }
+section { border-top: 1px solid #333; }
+figure { display: inline-block; vertical-align: top; margin: 0 1em 1em 0; width: 480px; }
+img { max-width: 100%; background: #000; }
+figcaption { color: #999; }
+pre { color: #ccc; white-space: pre-wrap; }
+.empty { color: #666; }
+</style>
+</head>
+<body>
+<h1>rustyart</h1>
+";
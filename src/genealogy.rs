@@ -0,0 +1,444 @@
+use crate::gallery::escape;
+use crate::sketches::{self, Lineage};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::hash::{Hash, Hasher};
+
+/// Sketches that share less than this with every earlier one start a new
+/// family.
+pub const MIN_SIMILARITY: f32 = 0.5;
+/// Unless they share at least this with one made shortly before, within
+/// `SESSION` seconds, which is more likely a rewrite than a fresh start.
+pub const SESSION_SIMILARITY: f32 = 0.25;
+pub const SESSION: i64 = 12 * 60 * 60;
+
+/// One sketch and where it most likely came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<String>,
+    /// How much of the source is shared with the parent, 0 to 1.
+    pub similarity: f32,
+    /// The parent was written down by `luna copy` instead of guessed.
+    pub recorded: bool,
+    /// Byte for byte the same as the parent.
+    pub duplicate: bool,
+    pub changes: Vec<String>,
+    /// Of the source, the same for byte identical sketches.
+    pub hash: u64,
+}
+
+/// Every sketch in `src/bin`, oldest first, with its inferred parent.
+#[derive(Clone, Debug, Default)]
+pub struct Genealogy {
+    pub nodes: Vec<Node>,
+}
+
+impl Genealogy {
+    pub fn load() -> Result<Self, String> {
+        let sources = sketches::list()
+            .into_iter()
+            .map(|name| {
+                let source = fs::read_to_string(sketches::source(&name)).unwrap_or_default();
+                (name, source)
+            })
+            .collect::<Vec<_>>();
+        Ok(Self::infer(&sources, &Lineage::load()?))
+    }
+
+    /// Picks each sketch's parent among the older ones, the one sharing the
+    /// most lines and the newest of equally similar ones, unless `lineage`
+    /// knows better. Rewrites are only traced within a session. `sources`
+    /// must be sorted by name, which is by time.
+    pub fn infer(sources: &[(String, String)], lineage: &Lineage) -> Self {
+        let lines = sources
+            .iter()
+            .map(|(_, source)| line_counts(source))
+            .collect::<Vec<_>>();
+        let times = sources
+            .iter()
+            .map(|(name, _)| sketches::timestamp(name))
+            .collect::<Vec<_>>();
+        let index = sources
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect::<HashMap<&str, usize>>();
+
+        let mut nodes = vec![];
+        for (i, (name, source)) in sources.iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            source.hash(&mut hasher);
+            let hash = hasher.finish();
+            let recorded = lineage
+                .parent(name)
+                .and_then(|parent| index.get(parent).copied());
+            let parent = match recorded {
+                Some(j) => Some((similarity(&lines[j], &lines[i]), j)),
+                None => (0..i)
+                    .map(|j| (similarity(&lines[j], &lines[i]), j))
+                    .filter(|(similarity, j)| {
+                        let recent = match (times[*j], times[i]) {
+                            (Some(then), Some(now)) => now - then <= SESSION,
+                            _ => false,
+                        };
+                        *similarity >= MIN_SIMILARITY
+                            || (recent && *similarity >= SESSION_SIMILARITY)
+                    })
+                    .max_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1))),
+            };
+            nodes.push(match parent {
+                Some((similarity, j)) => Node {
+                    name: name.clone(),
+                    parent: Some(sources[j].0.clone()),
+                    similarity,
+                    recorded: recorded.is_some(),
+                    duplicate: sources[j].1 == *source,
+                    changes: changes(&sources[j].1, source),
+                    hash,
+                },
+                None => Node {
+                    name: name.clone(),
+                    parent: None,
+                    similarity: 0.,
+                    recorded: false,
+                    duplicate: false,
+                    changes: vec![],
+                    hash,
+                },
+            });
+        }
+        Genealogy { nodes }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|node| node.parent.is_none())
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.nodes
+            .iter()
+            .filter(move |node| node.parent.as_deref() == Some(name))
+    }
+
+    /// How many sketches descend from `name`, itself included.
+    pub fn size(&self, name: &str) -> usize {
+        1 + self
+            .children(name)
+            .map(|child| self.size(&child.name))
+            .sum::<usize>()
+    }
+
+    /// Groups of byte identical sketches, oldest first, whatever their
+    /// parents.
+    pub fn duplicates(&self) -> Vec<Vec<&str>> {
+        let mut groups: HashMap<u64, Vec<&str>> = HashMap::new();
+        for node in self.nodes.iter() {
+            groups.entry(node.hash).or_default().push(&node.name);
+        }
+        let mut groups = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect::<Vec<_>>();
+        groups.sort();
+        groups
+    }
+
+    /// An indented tree. Every sketch continues the line of its parent's
+    /// largest branch, the other branches are indented below the parent.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for root in self.roots() {
+            self.write_text(&mut out, root, "", "");
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Writes the line of descent from `node`, starting with `first` and
+    /// continuing with `indent`.
+    fn write_text(&self, out: &mut String, node: &Node, indent: &str, first: &str) {
+        let mut node = node;
+        let mut prefix = first;
+        loop {
+            match node.parent {
+                Some(_) => writeln!(out, "{}{}  {}", prefix, node.name, edge_label(node)).unwrap(),
+                None => writeln!(out, "{}{}", prefix, node.name).unwrap(),
+            }
+            prefix = indent;
+            let (main, branches) = self.branches(&node.name);
+            for branch in branches {
+                let branch_indent = format!("{}    ", indent);
+                self.write_text(out, branch, &branch_indent, &format!("{}  └ ", indent));
+            }
+            node = match main {
+                Some(main) => main,
+                None => return,
+            };
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph genealogy {\n    rankdir=LR;\n    node [shape=box, fontname=monospace];\n",
+        );
+        for node in self.nodes.iter() {
+            writeln!(out, "    \"{}\";", node.name).unwrap();
+        }
+        for node in self.nodes.iter() {
+            let parent = match &node.parent {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let label = edge_label(node);
+            let style = match (node.duplicate, node.recorded) {
+                (true, _) => ", style=dashed, color=red",
+                (false, true) => ", style=bold",
+                (false, false) => "",
+            };
+            writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"{}];",
+                parent,
+                node.name,
+                label.replace('\\', "\\\\").replace('"', "\\\""),
+                style
+            )
+            .unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// A standalone page with the tree as nested lists, every sketch linking
+    /// to its parent and listing what changed.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(HTML_HEAD);
+        let duplicates = self.duplicates();
+        if !duplicates.is_empty() {
+            out.push_str("<h2>duplicates</h2>\n<ul>\n");
+            for group in duplicates {
+                let links = group
+                    .iter()
+                    .map(|name| format!("<a href=\"#{0}\">{0}</a>", name))
+                    .collect::<Vec<_>>();
+                writeln!(out, "<li>{}</li>", links.join(" = ")).unwrap();
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("<h2>tree</h2>\n<ul>\n");
+        for root in self.roots() {
+            self.write_html(&mut out, root);
+        }
+        out.push_str("</ul>\n</body>\n</html>\n");
+        out
+    }
+
+    /// Writes the line of descent from `node` as items of one list, side
+    /// branches as lists nested in the item they branch off.
+    fn write_html(&self, out: &mut String, node: &Node) {
+        let mut node = Some(node);
+        while let Some(current) = node {
+            write!(
+                out,
+                "<li id=\"{0}\"><details><summary><b>{0}</b>",
+                current.name
+            )
+            .unwrap();
+            if let Some(parent) = &current.parent {
+                write!(
+                    out,
+                    " <a href=\"#{0}\">from {0}</a> <span>{1}</span>",
+                    parent,
+                    escape(&edge_label(current))
+                )
+                .unwrap();
+            }
+            out.push_str("</summary>\n");
+            if !current.changes.is_empty() {
+                out.push_str("<ol>");
+                for change in current.changes.iter() {
+                    write!(out, "<li><code>{}</code></li>", escape(change)).unwrap();
+                }
+                out.push_str("</ol>\n");
+            }
+            out.push_str("</details>\n");
+            let (main, branches) = self.branches(&current.name);
+            if !branches.is_empty() {
+                out.push_str("<ul>\n");
+                for branch in branches {
+                    self.write_html(out, branch);
+                }
+                out.push_str("</ul>\n");
+            }
+            out.push_str("</li>\n");
+            node = main;
+        }
+    }
+
+    /// The child continuing the line of descent, the one with the most
+    /// descendants and the oldest of equally large ones, and the others.
+    fn branches<'a>(&'a self, name: &'a str) -> (Option<&'a Node>, Vec<&'a Node>) {
+        let mut children = self.children(name).collect::<Vec<_>>();
+        let main = children
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, child)| (self.size(&child.name), usize::MAX - i))
+            .map(|(i, _)| i);
+        (main.map(|i| children.remove(i)), children)
+    }
+}
+
+const HTML_HEAD: &str = "<!doctype html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>sketch genealogy</title>
+<style>
+body { background: #181818; color: #ddd; font: 14px monospace; }
+a { color: #8bf; }
+span { color: #999; }
+ul { list-style: none; padding-left: 1.5em; border-left: 1px solid #333; }
+ol { color: #aaa; margin: 0.2em 0; }
+:target > details > summary { background: #333; }
+</style>
+</head>
+<body>
+<h1>sketch genealogy</h1>
+";
+
+/// Similarity in percent and the first few changes.
+pub fn edge_label(node: &Node) -> String {
+    let mut label = format!("{:.0}%", node.similarity * 100.);
+    if node.recorded {
+        label.push_str(" copied");
+    }
+    if node.duplicate {
+        label.push_str(" duplicate");
+    } else if node.changes.is_empty() {
+        label.push_str(" formatting");
+    } else {
+        let shown = node
+            .changes
+            .iter()
+            .take(4)
+            .map(|change| match change.char_indices().nth(48) {
+                Some((end, _)) => format!("{}...", &change[..end]),
+                None => change.clone(),
+            })
+            .collect::<Vec<_>>();
+        write!(label, "  {}", shown.join("; ")).unwrap();
+        if node.changes.len() > 4 {
+            write!(label, "; {} more", node.changes.len() - 4).unwrap();
+        }
+    }
+    label
+}
+
+fn line_counts(source: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for line in source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        *counts.entry(line).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// The Dice coefficient of the trimmed, non empty lines.
+fn similarity(a: &HashMap<&str, usize>, b: &HashMap<&str, usize>) -> f32 {
+    let shared = a
+        .iter()
+        .map(|(line, count)| b.get(line).map_or(0, |other| *count.min(other)))
+        .sum::<usize>();
+    let total = a.values().sum::<usize>() + b.values().sum::<usize>();
+    match total {
+        0 => 1.,
+        _ => 2. * shared as f32 / total as f32,
+    }
+}
+
+/// What changed between two sketches: constants, the gradient and which
+/// functions were added, removed or rewritten.
+pub fn changes(old: &str, new: &str) -> Vec<String> {
+    let mut changes =
+        sketches::diff_constants(&sketches::constants(old), &sketches::constants(new));
+    let (old_stops, new_stops) = (gradient(old), gradient(new));
+    if old_stops != new_stops {
+        changes.push(match (old_stops.len(), new_stops.len()) {
+            (0, n) => format!("+gradient {} stops", n),
+            (_, 0) => "-gradient".to_owned(),
+            (a, b) if a != b => format!("gradient {} -> {} stops", a, b),
+            _ => "gradient recoloured".to_owned(),
+        });
+    }
+    let old_functions = functions(old);
+    let new_functions = functions(new);
+    for (name, body) in new_functions.iter() {
+        match old_functions.get(name) {
+            Some(old_body) if old_body != body => changes.push(format!("fn {}", name)),
+            Some(_) => (),
+            None => changes.push(format!("+fn {}", name)),
+        }
+    }
+    for name in old_functions.keys() {
+        if !new_functions.contains_key(name) {
+            changes.push(format!("-fn {}", name));
+        }
+    }
+    changes
+}
+
+/// The stops of the first `Gradient::...(vec![...])`, one per line with
+/// whitespace removed.
+fn gradient(source: &str) -> Vec<String> {
+    let start = match source.find("Gradient::") {
+        Some(start) => start,
+        None => return vec![],
+    };
+    let rest = &source[start..];
+    let end = rest.find("])").map_or(rest.len(), |end| end + 2);
+    rest[..end]
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<String>())
+        .filter(|line| line.starts_with('(') && line != "(")
+        .collect()
+}
+
+/// Top level function bodies by name, comments dropped and whitespace
+/// collapsed so reformatting alone does not count as a change.
+fn functions(source: &str) -> BTreeMap<String, String> {
+    let mut functions = BTreeMap::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let name = match line
+            .strip_prefix("fn ")
+            .or_else(|| line.strip_prefix("pub fn "))
+        {
+            Some(rest) => rest
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+                .unwrap_or_default(),
+            None => continue,
+        };
+        let mut body = String::new();
+        for line in lines.by_ref() {
+            if line == "}" {
+                break;
+            }
+            let code = line.split("//").next().unwrap_or_default();
+            body.extend(code.split_whitespace());
+        }
+        functions.insert(name.to_owned(), body);
+    }
+    functions
+}
@@ -0,0 +1,351 @@
+use crate::gui::Ui;
+use crate::links::{edges, key};
+use crate::presets::Lerp;
+use crate::spatial::Grid;
+use delaunator::{next_halfedge, Triangulation, EMPTY};
+use nannou::prelude::*;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Edge sets derived from the Delaunay triangulation. Every one but
+/// `Nearest` is a subgraph of the one before it in this order, the
+/// β-skeleton while β is at most 2, from the Delaunay graph down to the
+/// spanning tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Graph {
+    /// Every edge of the triangulation.
+    #[default]
+    Delaunay,
+    /// Edges whose diameter circle holds no other point.
+    Gabriel,
+    /// The lune based β-skeleton, between Gabriel at β 1 and the relative
+    /// neighbourhood graph at β 2, sparser above.
+    Skeleton,
+    /// Edges with no point closer to both ends than they are to each other.
+    RelativeNeighbourhood,
+    /// The Euclidean minimum spanning tree.
+    SpanningTree,
+    /// Every point to its `k` nearest others.
+    Nearest,
+}
+
+impl Graph {
+    pub const ALL: [Graph; 6] = [
+        Graph::Delaunay,
+        Graph::Gabriel,
+        Graph::Skeleton,
+        Graph::RelativeNeighbourhood,
+        Graph::SpanningTree,
+        Graph::Nearest,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Graph::Delaunay => "delaunay",
+            Graph::Gabriel => "gabriel",
+            Graph::Skeleton => "β-skeleton",
+            Graph::RelativeNeighbourhood => "relative neighbourhood",
+            Graph::SpanningTree => "spanning tree",
+            Graph::Nearest => "k nearest",
+        }
+    }
+
+    /// The next graph in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&graph| graph == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// Which graph links follow, with the parameters of the ones that have any.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphSettings {
+    pub graph: Graph,
+    /// Neighbours per point for `Graph::Nearest`.
+    pub k: usize,
+    /// β of `Graph::Skeleton`, at least 1.
+    pub beta: f32,
+}
+
+impl Default for GraphSettings {
+    fn default() -> Self {
+        GraphSettings {
+            graph: Graph::Delaunay,
+            k: 3,
+            beta: 1.5,
+        }
+    }
+}
+
+impl GraphSettings {
+    /// The edges of the graph over `points`, which `triangulation` has to be
+    /// made of, each once as a pair of point indices.
+    pub fn edges(&self, points: &[Vec2], triangulation: &Triangulation) -> Vec<(usize, usize)> {
+        match self.graph {
+            Graph::Delaunay => edges(triangulation).collect(),
+            Graph::Gabriel => gabriel(points, triangulation),
+            Graph::Skeleton => skeleton(points, triangulation, self.beta),
+            Graph::RelativeNeighbourhood => skeleton(points, triangulation, 2.),
+            Graph::SpanningTree => spanning_tree(points, triangulation),
+            Graph::Nearest => nearest(points, triangulation, self.k),
+        }
+    }
+
+    /// A button cycling through the graphs and sliders for their parameters.
+    /// True if anything changed.
+    pub fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        if ui.button(&format!("graph: {}", self.graph.name())) {
+            self.graph = self.graph.next();
+            changed = true;
+        }
+        match self.graph {
+            Graph::Skeleton => changed |= ui.slider("β", &mut self.beta, 1.0..=4.),
+            Graph::Nearest => changed |= ui.count("k", &mut self.k, 1..=12),
+            _ => (),
+        }
+        changed
+    }
+}
+
+/// Switches graphs half way, the parameters blend.
+impl Lerp for GraphSettings {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        GraphSettings {
+            graph: if t < 0.5 { self.graph } else { to.graph },
+            k: self.k.lerp(&to.k, t),
+            beta: self.beta.lerp(&to.beta, t),
+        }
+    }
+}
+
+/// A Delaunay edge is a Gabriel edge exactly when neither point opposite it
+/// sees it at a right angle or more.
+pub fn gabriel(points: &[Vec2], triangulation: &Triangulation) -> Vec<(usize, usize)> {
+    let opposite_sees_acute = |halfedge: usize| {
+        let a = points[triangulation.triangles[halfedge]];
+        let b = points[triangulation.triangles[next_halfedge(halfedge)]];
+        let c = points[triangulation.triangles[next_halfedge(next_halfedge(halfedge))]];
+        (a - c).dot(b - c) > 0.
+    };
+    (0..triangulation.triangles.len())
+        .filter(|&i| {
+            let twin = triangulation.halfedges[i];
+            (i > twin || twin == EMPTY)
+                && opposite_sees_acute(i)
+                && (twin == EMPTY || opposite_sees_acute(twin))
+        })
+        .map(|i| {
+            (
+                triangulation.triangles[i],
+                triangulation.triangles[next_halfedge(i)],
+            )
+        })
+        .collect()
+}
+
+/// Edges whose lune, the intersection of the two circles of radius
+/// `β * length / 2` through both ends, holds no other point. `beta` below 1
+/// counts as 1.
+pub fn skeleton(points: &[Vec2], triangulation: &Triangulation, beta: f32) -> Vec<(usize, usize)> {
+    let beta = beta.max(1.);
+    let grid = Grid::new(points.iter().copied());
+    // Every β >= 1 skeleton is a subgraph of the Gabriel graph.
+    gabriel(points, triangulation)
+        .into_iter()
+        .filter(|&(a, b)| {
+            let (p, q) = (points[a], points[b]);
+            let length = p.distance(q);
+            let radius = beta * length / 2.;
+            let centers = (p.lerp(q, beta / 2.), q.lerp(p, beta / 2.));
+            grid.within_radius(p.lerp(q, 0.5), (beta - 0.5) * length)
+                .into_iter()
+                .filter(|&other| other != a && other != b)
+                .all(|other| {
+                    let point = points[other];
+                    point.distance(centers.0) >= radius || point.distance(centers.1) >= radius
+                })
+        })
+        .collect()
+}
+
+/// Kruskal's algorithm over the Delaunay edges, which always contain the
+/// Euclidean minimum spanning tree.
+pub fn spanning_tree(points: &[Vec2], triangulation: &Triangulation) -> Vec<(usize, usize)> {
+    let mut candidates = edges(triangulation).collect::<Vec<_>>();
+    candidates.sort_by_key(|&(a, b)| OrderedFloat(points[a].distance_squared(points[b])));
+
+    let mut parents = (0..points.len()).collect::<Vec<usize>>();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    let mut tree = vec![];
+    for (a, b) in candidates {
+        let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+        if root_a != root_b {
+            parents[root_a] = root_b;
+            tree.push((a, b));
+        }
+    }
+    tree
+}
+
+/// Each point to its `k` nearest others. The `j`th nearest neighbour of a
+/// point always shares a Delaunay edge with the point or one of its closer
+/// neighbours, so a best first search along the triangulation finds them.
+pub fn nearest(points: &[Vec2], triangulation: &Triangulation, k: usize) -> Vec<(usize, usize)> {
+    let mut neighbours = vec![vec![]; points.len()];
+    for (a, b) in edges(triangulation) {
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+    }
+
+    let mut found = HashSet::new();
+    for (point, position) in points.iter().enumerate() {
+        let distance = |other: usize| OrderedFloat(position.distance_squared(points[other]));
+        let mut seen = HashSet::from([point]);
+        let mut queue = BinaryHeap::new();
+        for &other in &neighbours[point] {
+            seen.insert(other);
+            queue.push(Reverse((distance(other), other)));
+        }
+        for _ in 0..k {
+            let Some(Reverse((_, other))) = queue.pop() else {
+                break;
+            };
+            found.insert(key(point, other));
+            for &next in &neighbours[other] {
+                if seen.insert(next) {
+                    queue.push(Reverse((distance(next), next)));
+                }
+            }
+        }
+    }
+    let mut found = found.into_iter().collect::<Vec<_>>();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::{random_point_in_radius, seeded};
+    use delaunator::{triangulate, Point};
+
+    fn points(seed: u64) -> (Vec<Vec2>, Triangulation) {
+        let mut rng = seeded(seed);
+        let points = (0..300)
+            .map(|_| random_point_in_radius(&mut rng, &Vec2::ZERO, 1000.))
+            .collect::<Vec<Vec2>>();
+        let triangulation = triangulate(
+            &points
+                .iter()
+                .map(|point| Point {
+                    x: point.x as f64,
+                    y: point.y as f64,
+                })
+                .collect::<Vec<Point>>(),
+        );
+        (points, triangulation)
+    }
+
+    fn keys(edges: impl IntoIterator<Item = (usize, usize)>) -> HashSet<(usize, usize)> {
+        edges.into_iter().map(|(a, b)| key(a, b)).collect()
+    }
+
+    #[test]
+    fn each_graph_is_a_subgraph_of_the_next() {
+        for seed in 0..5 {
+            let (points, triangulation) = points(seed);
+            let tree = keys(spanning_tree(&points, &triangulation));
+            let relative = keys(skeleton(&points, &triangulation, 2.));
+            let gabriel = keys(gabriel(&points, &triangulation));
+            let delaunay = keys(edges(&triangulation));
+            assert!(tree.is_subset(&relative));
+            assert!(relative.is_subset(&gabriel));
+            assert!(gabriel.is_subset(&delaunay));
+            assert!(relative.len() < gabriel.len());
+        }
+    }
+
+    #[test]
+    fn gabriel_matches_brute_force() {
+        let (points, triangulation) = points(5);
+        let expected = (0..points.len())
+            .flat_map(|a| (a + 1..points.len()).map(move |b| (a, b)))
+            .filter(|&(a, b)| {
+                // No other point inside the circle with the edge as diameter.
+                (0..points.len()).all(|c| {
+                    c == a || c == b || (points[a] - points[c]).dot(points[b] - points[c]) > 0.
+                })
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(keys(gabriel(&points, &triangulation)), expected);
+    }
+
+    #[test]
+    fn relative_neighbourhood_matches_brute_force() {
+        let (points, triangulation) = points(6);
+        let expected = (0..points.len())
+            .flat_map(|a| (a + 1..points.len()).map(move |b| (a, b)))
+            .filter(|&(a, b)| {
+                let length = points[a].distance(points[b]);
+                (0..points.len()).all(|c| {
+                    c == a
+                        || c == b
+                        || points[c]
+                            .distance(points[a])
+                            .max(points[c].distance(points[b]))
+                            >= length
+                })
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(keys(skeleton(&points, &triangulation, 2.)), expected);
+    }
+
+    #[test]
+    fn spanning_tree_connects_everything() {
+        let (points, triangulation) = points(7);
+        let tree = spanning_tree(&points, &triangulation);
+        assert_eq!(tree.len(), points.len() - 1);
+        let mut reached = HashSet::from([0]);
+        let mut stack = vec![0];
+        while let Some(point) = stack.pop() {
+            for &(a, b) in tree.iter() {
+                let other = if point == a {
+                    b
+                } else if point == b {
+                    a
+                } else {
+                    continue;
+                };
+                if reached.insert(other) {
+                    stack.push(other);
+                }
+            }
+        }
+        assert_eq!(reached.len(), points.len());
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let (points, triangulation) = points(8);
+        let k = 4;
+        let mut expected = HashSet::new();
+        for (a, point) in points.iter().enumerate() {
+            let mut others = (0..points.len()).filter(|&b| b != a).collect::<Vec<_>>();
+            others.sort_by_key(|&b| OrderedFloat(point.distance_squared(points[b])));
+            expected.extend(others[..k].iter().map(|&b| key(a, b)));
+        }
+        assert_eq!(keys(nearest(&points, &triangulation, k)), expected);
+    }
+}
@@ -0,0 +1,214 @@
+use nannou::color::{IntoLinSrgba, LinSrgba};
+use nannou::prelude::*;
+use std::ops::RangeInclusive;
+
+const WIDTH: f32 = 340.;
+const LABEL_WIDTH: f32 = 120.;
+const ROW: f32 = 18.;
+const GAP: f32 = 4.;
+const MARGIN: f32 = 20.;
+const PADDING: f32 = 8.;
+
+/// A panel of sliders in the top right corner of the window. Sketches build
+/// it every `update` through `Ui`, which applies the mouse right away, and
+/// draw the result in `view`.
+#[derive(Clone, Debug, Default)]
+pub struct Panel {
+    pub visible: bool,
+    active: Option<usize>,
+    was_down: bool,
+    shapes: Vec<Shape>,
+}
+
+#[derive(Clone, Debug)]
+enum Shape {
+    Rect(Rect, LinSrgba),
+    Text(Rect, String),
+}
+
+impl Panel {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Starts this frame's panel, the widgets are laid out top to bottom.
+    pub fn ui(&mut self, app: &App) -> Ui<'_> {
+        self.ui_at(
+            app.window_rect(),
+            app.mouse.position(),
+            app.mouse.buttons.left().is_down(),
+        )
+    }
+
+    /// `ui` for a window `win` with the mouse at `mouse`.
+    pub fn ui_at(&mut self, win: Rect, mouse: Vec2, down: bool) -> Ui<'_> {
+        let down = self.visible && down;
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+        if !down {
+            self.active = None;
+        }
+        self.shapes.clear();
+        let top_left = pt2(win.right() - MARGIN - WIDTH, win.top() - MARGIN);
+        Ui {
+            panel: self,
+            mouse,
+            down,
+            pressed,
+            top_left,
+            y: top_left.y - PADDING,
+            id: 0,
+        }
+    }
+
+    pub fn draw(&self, draw: &Draw) {
+        if !self.visible {
+            return;
+        }
+        for shape in self.shapes.iter() {
+            match shape {
+                Shape::Rect(rect, color) => {
+                    draw.rect().xy(rect.xy()).wh(rect.wh()).color(*color);
+                }
+                Shape::Text(rect, text) => {
+                    draw.text(text)
+                        .xy(rect.xy())
+                        .wh(rect.wh())
+                        .left_justify()
+                        .font_size(12)
+                        .color(WHITE);
+                }
+            }
+        }
+    }
+}
+
+/// Builds one frame of a `Panel`. Every widget returns whether the user
+/// changed its value in this frame.
+pub struct Ui<'a> {
+    panel: &'a mut Panel,
+    mouse: Vec2,
+    down: bool,
+    pressed: bool,
+    top_left: Vec2,
+    y: f32,
+    id: usize,
+}
+
+impl<'a> Ui<'a> {
+    pub fn label(&mut self, text: &str) {
+        let row = self.row();
+        self.text(row, text);
+    }
+
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        self.slider_with(label, value, range, |value| format!("{:.3}", value))
+    }
+
+    /// A slider over whole numbers.
+    pub fn count(&mut self, label: &str, value: &mut usize, range: RangeInclusive<usize>) -> bool {
+        let mut float = *value as f32;
+        let range = *range.start() as f32..=*range.end() as f32;
+        self.slider_with(label, &mut float, range, |value| {
+            format!("{}", value.round())
+        });
+        let rounded = float.round() as usize;
+        let changed = rounded != *value;
+        *value = rounded;
+        changed
+    }
+
+    /// A label with a patch of `color`, for the sliders below it.
+    pub fn swatch(&mut self, label: &str, color: impl IntoLinSrgba<f32>) {
+        let (label_rect, control) = split(self.row());
+        self.text(label_rect, label);
+        self.rect(control, color.into_lin_srgba());
+    }
+
+    pub fn button(&mut self, label: &str) -> bool {
+        let row = self.row();
+        let (_, control) = split(row);
+        let clicked = self.pressed && control.contains(self.mouse);
+        self.interact(control);
+        self.rect(control, lin_srgba(0.25, 0.25, 0.25, 1.));
+        self.text(control.pad_left(4.), label);
+        clicked
+    }
+
+    fn slider_with(
+        &mut self,
+        label: &str,
+        value: &mut f32,
+        range: RangeInclusive<f32>,
+        format: impl Fn(f32) -> String,
+    ) -> bool {
+        let (label_rect, track) = split(self.row());
+        let (start, end) = (*range.start(), *range.end());
+        let mut changed = false;
+        if self.interact(track) {
+            let t = ((self.mouse.x - track.left()) / track.w()).clamp(0., 1.);
+            let dragged = start + t * (end - start);
+            changed = dragged != *value;
+            *value = dragged;
+        }
+        let t = ((*value - start) / (end - start)).clamp(0., 1.);
+        let fill = Rect::from_corners(
+            track.bottom_left(),
+            pt2(track.left() + track.w() * t, track.top()),
+        );
+        self.text(label_rect, label);
+        self.rect(track, lin_srgba(0.15, 0.15, 0.15, 1.));
+        self.rect(fill, lin_srgba(0.35, 0.35, 0.35, 1.));
+        self.text(track.pad_left(4.), &format(*value));
+        changed
+    }
+
+    /// True while the mouse drags the widget in `rect`. Each widget calls
+    /// this once, in the same order every frame, so the order is its id.
+    fn interact(&mut self, rect: Rect) -> bool {
+        let id = self.id;
+        self.id += 1;
+        if self.pressed && rect.contains(self.mouse) {
+            self.panel.active = Some(id);
+        }
+        self.down && self.panel.active == Some(id)
+    }
+
+    fn row(&mut self) -> Rect {
+        let left = self.top_left.x + PADDING;
+        let row = Rect::from_corners(
+            pt2(left, self.y - ROW),
+            pt2(left + WIDTH - 2. * PADDING, self.y),
+        );
+        self.y -= ROW + GAP;
+        row
+    }
+
+    fn rect(&mut self, rect: Rect, color: LinSrgba) {
+        self.panel.shapes.push(Shape::Rect(rect, color));
+    }
+
+    fn text(&mut self, rect: Rect, text: &str) {
+        self.panel.shapes.push(Shape::Text(rect, text.to_owned()));
+    }
+}
+
+/// Label column and control column of a row.
+fn split(row: Rect) -> (Rect, Rect) {
+    let label = Rect::from_corners(row.bottom_left(), pt2(row.left() + LABEL_WIDTH, row.top()));
+    let control = Rect::from_corners(pt2(label.right(), row.bottom()), row.top_right());
+    (label, control)
+}
+
+/// Puts the panel's background behind everything built.
+impl<'a> Drop for Ui<'a> {
+    fn drop(&mut self) {
+        let background = Rect::from_corners(
+            pt2(self.top_left.x, self.y + GAP - PADDING),
+            pt2(self.top_left.x + WIDTH, self.top_left.y),
+        );
+        self.panel
+            .shapes
+            .insert(0, Shape::Rect(background, lin_srgba(0.05, 0.05, 0.05, 0.9)));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::args::Args;
+use crate::render::Raster;
+use nannou::geom::Rect;
+use nannou::image::ImageResult;
+use std::path::PathBuf;
+
+/// Renders a sketch into a `Raster` without a window or GPU. One sketch unit
+/// is one pixel, like a window without display scaling.
+#[derive(Clone, Debug)]
+pub struct Headless {
+    pub path: PathBuf,
+    pub frames: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Headless {
+    /// Set up when `--render` was given, 600 frames at 1920x1080 by default.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let (width, height) = args.size.unwrap_or((1920, 1080));
+        Some(Headless {
+            path: args.render.clone()?,
+            frames: args.frames.unwrap_or(600),
+            width,
+            height,
+        })
+    }
+
+    pub fn raster(&self) -> Raster {
+        Raster::new(
+            self.width,
+            self.height,
+            Rect::from_w_h(self.width as f32, self.height as f32),
+        )
+    }
+
+    /// Calls `frame` once per frame on the same raster, then saves it.
+    pub fn run(&self, mut frame: impl FnMut(&mut Raster)) -> ImageResult<()> {
+        let mut raster = self.raster();
+        for _ in 0..self.frames {
+            frame(&mut raster);
+        }
+        raster.save(&self.path)?;
+        println!("rendered {}", self.path.display());
+        Ok(())
+    }
+}
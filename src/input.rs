@@ -0,0 +1,175 @@
+use crate::capture::capture;
+use crate::clock::Clock;
+use crate::gui::Panel;
+use crate::metadata::Metadata;
+use crate::params;
+use crate::registry;
+use nannou::prelude::*;
+use serde::Serialize;
+
+/// State behind the controls every sketch shares.
+#[derive(Clone, Debug)]
+pub struct Controls {
+    pub info: bool,
+    pub seed: u64,
+    pub panel: Panel,
+    /// The sketch's current parameters as TOML, embedded into captures.
+    pub params: Option<String>,
+    /// S was pressed and the next frame is to be captured.
+    pub capture: bool,
+    /// A line the sketch shows in the bottom left corner, like the preset it
+    /// switches to.
+    pub status: Option<String>,
+}
+
+impl Controls {
+    pub fn new(seed: u64) -> Self {
+        Controls {
+            info: false,
+            seed,
+            panel: Panel::default(),
+            params: None,
+            capture: false,
+            status: None,
+        }
+    }
+
+    pub fn set_params(&mut self, params: &impl Serialize) {
+        self.params = Some(params::to_toml(params).expect("params are not toml"));
+    }
+}
+
+/// S captures the frame, F freezes the clock, . runs a single step, + and -
+/// double or halve the clock speed, I toggles the info overlay and Tab the
+/// parameter panel. Sketches that draw on a `Canvas` bind V to
+/// `capture::capture_svg` themselves.
+pub fn key_released(controls: &mut Controls, clock: &mut Clock, key: Key) {
+    match key {
+        Key::S => {
+            controls.capture = true;
+        }
+        Key::F => {
+            clock.toggle_pause();
+        }
+        Key::Period => {
+            clock.single_step();
+        }
+        Key::Equals | Key::Plus | Key::NumpadAdd => {
+            clock.scale *= 2.;
+        }
+        Key::Minus | Key::NumpadSubtract => {
+            clock.scale /= 2.;
+        }
+        Key::I => {
+            controls.info = !controls.info;
+        }
+        Key::Tab => {
+            controls.panel.toggle();
+        }
+        _ => (),
+    }
+}
+
+/// Captures the frame about to be drawn if S asked for it, with the step it
+/// shows. Call at the end of `update`, after the simulation stepped.
+pub fn capture_requested(app: &App, controls: &mut Controls, clock: &Clock) {
+    if !controls.capture {
+        return;
+    }
+    controls.capture = false;
+    let metadata = Metadata::new(
+        registry::name(app),
+        controls.seed,
+        clock.steps(),
+        controls.params.clone(),
+    );
+    capture(app, metadata);
+}
+
+/// Delete held down clears the canvas.
+pub fn clear_requested(app: &App) -> bool {
+    app.keys.down.contains(&Key::Delete)
+}
+
+/// Seed and clock in the top left corner while the info overlay is on.
+pub fn draw_info(app: &App, draw: &Draw, controls: &Controls, clock: &Clock) {
+    if !controls.info {
+        return;
+    }
+    let win = app.window_rect().pad(20.);
+    let info = format!(
+        "seed {}\ntime {:.2}s\nstep {}\nscale {}{}",
+        controls.seed,
+        clock.time(),
+        clock.steps(),
+        clock.scale,
+        if clock.paused { "\npaused" } else { "" },
+    );
+    draw.text(&info)
+        .xy(win.xy())
+        .wh(win.wh())
+        .left_justify()
+        .align_text_top()
+        .font_size(16)
+        .color(WHITE);
+}
+
+/// The sketch's status line in the bottom left corner.
+pub fn draw_status(app: &App, draw: &Draw, controls: &Controls) {
+    let status = match &controls.status {
+        Some(status) => status,
+        None => return,
+    };
+    let win = app.window_rect().pad(20.);
+    draw.text(status)
+        .xy(win.xy())
+        .wh(win.wh())
+        .left_justify()
+        .align_text_bottom()
+        .font_size(16)
+        .color(WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_drive_the_clock() {
+        let mut controls = Controls::new(1);
+        let mut clock = Clock::default();
+        key_released(&mut controls, &mut clock, Key::F);
+        assert!(clock.paused);
+        key_released(&mut controls, &mut clock, Key::Period);
+        assert_eq!(clock.advance(std::time::Duration::ZERO), 1);
+        key_released(&mut controls, &mut clock, Key::Equals);
+        key_released(&mut controls, &mut clock, Key::Equals);
+        key_released(&mut controls, &mut clock, Key::Minus);
+        assert_eq!(clock.scale, 2.);
+    }
+
+    #[test]
+    fn keys_toggle_controls() {
+        let mut controls = Controls::new(1);
+        let mut clock = Clock::default();
+        key_released(&mut controls, &mut clock, Key::S);
+        key_released(&mut controls, &mut clock, Key::I);
+        key_released(&mut controls, &mut clock, Key::Tab);
+        assert!(controls.capture);
+        assert!(controls.info);
+        assert!(controls.panel.visible);
+        key_released(&mut controls, &mut clock, Key::I);
+        assert!(!controls.info);
+    }
+
+    #[test]
+    fn set_params_keeps_them_as_toml() {
+        #[derive(Serialize)]
+        struct Params {
+            count: usize,
+        }
+        let mut controls = Controls::new(1);
+        controls.set_params(&Params { count: 3 });
+        assert_eq!(controls.params.as_deref(), Some("count = 3\n"));
+    }
+}
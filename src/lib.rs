@@ -0,0 +1,29 @@
+pub mod alpha_shape;
+pub mod args;
+pub mod capture;
+pub mod clock;
+pub mod gallery;
+pub mod genealogy;
+pub mod graph;
+pub mod gui;
+pub mod headless;
+pub mod input;
+pub mod links;
+pub mod metadata;
+pub mod params;
+pub mod particles;
+pub mod plot;
+pub mod poster;
+pub mod presets;
+pub mod registry;
+pub mod render;
+pub mod runner;
+pub mod sampling;
+pub mod sketches;
+pub mod spatial;
+pub mod svg;
+pub mod sweep;
+pub mod trails;
+pub mod triangles;
+pub mod video;
+pub mod voronoi;
@@ -0,0 +1,271 @@
+use crate::particles::Particle;
+use delaunator::{next_halfedge, triangulate, Point, Triangulation, EMPTY};
+use nannou::ease::*;
+use nannou::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Link {
+    pub a: usize,
+    pub b: usize,
+    pub since: f64,
+    pub until: Option<f64>,
+}
+
+impl Link {
+    /// `now` is the simulation time, see `clock::Clock`.
+    pub fn new(a: usize, b: usize, now: f64) -> Self {
+        Link {
+            a,
+            b,
+            since: now,
+            until: None,
+        }
+    }
+
+    pub fn connects(&self, a: usize, b: usize) -> bool {
+        a == self.a && b == self.b || a == self.b && b == self.a
+    }
+
+    pub fn key(&self) -> (usize, usize) {
+        key(self.a, self.b)
+    }
+
+    pub fn alive(&self) -> bool {
+        self.until.is_none()
+    }
+
+    /// Seconds since the link was born.
+    pub fn age(&self, now: f64) -> f32 {
+        (now - self.since) as f32
+    }
+
+    /// Seconds since the link died, `None` while it is alive.
+    pub fn dead_for(&self, now: f64) -> Option<f32> {
+        self.until.map(|until| (now - until) as f32)
+    }
+
+    /// Eases from 0 at birth to 1 after `duration` seconds.
+    pub fn fade_in(&self, now: f64, duration: f32) -> f32 {
+        let since_mapped =
+            map_range::<f32, f32>(self.age(now), 0.0, duration, 1., 0.).clamp(0., 1.);
+        1. - cubic::ease_out(since_mapped, 0., 1., 1.)
+    }
+
+    /// Eases from 1 at death to 0 after `duration` seconds, 1 while alive.
+    pub fn fade_out(&self, now: f64, duration: f32) -> f32 {
+        match self.dead_for(now) {
+            Some(dead_for) => {
+                let dead_mapped =
+                    map_range::<f32, f32>(dead_for, 0.0, duration, 0., 1.).clamp(0., 1.);
+                1. - cubic::ease_out(dead_mapped, 0., 1., 1.)
+            }
+            None => 1.,
+        }
+    }
+}
+
+/// Unordered particle pair, smaller index first.
+pub fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+pub fn triangulate_particles(particles: &[Particle]) -> Triangulation {
+    let points = particles
+        .iter()
+        .map(|particle| Point {
+            x: particle.position.x as f64,
+            y: particle.position.y as f64,
+        })
+        .collect::<Vec<Point>>();
+
+    triangulate(&points)
+}
+
+/// Every edge of the triangulation exactly once, as a pair of point indices.
+pub fn edges(triangulation: &Triangulation) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..triangulation.triangles.len())
+        .filter(|&i| i > triangulation.halfedges[i] || triangulation.halfedges[i] == EMPTY)
+        .map(|i| {
+            (
+                triangulation.triangles[i],
+                triangulation.triangles[next_halfedge(i)],
+            )
+        })
+}
+
+/// Links for the current triangulation. Links that already existed in
+/// `previous` keep their `since`, new ones start at `now`.
+pub fn update_links(previous: &[Link], triangulation: &Triangulation, now: f64) -> Vec<Link> {
+    let previous = previous
+        .iter()
+        .map(|link| (link.key(), *link))
+        .collect::<HashMap<(usize, usize), Link>>();
+
+    edges(triangulation)
+        .map(|(a, b)| match previous.get(&key(a, b)) {
+            Some(link) => *link,
+            None => Link::new(a, b, now),
+        })
+        .collect()
+}
+
+/// Follows links across triangulations. Links that leave the triangulation
+/// are kept as dead for `fade_out` seconds so they can be eased out.
+#[derive(Clone, Debug)]
+pub struct LinkTracker {
+    pub fade_out: f32,
+    links: Vec<Link>,
+    index: HashMap<(usize, usize), usize>,
+}
+
+impl LinkTracker {
+    pub fn new(fade_out: f32) -> Self {
+        LinkTracker {
+            fade_out,
+            links: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    /// Alive links come first in edge order, followed by the dead ones that
+    /// are still fading out. Returns the links that died in this update.
+    pub fn update(
+        &mut self,
+        edges: impl IntoIterator<Item = (usize, usize)>,
+        now: f64,
+    ) -> Vec<Link> {
+        let mut links = vec![];
+        let mut index = HashMap::new();
+
+        for (a, b) in edges {
+            let key = key(a, b);
+            if index.contains_key(&key) {
+                continue;
+            }
+            let link = match self.get(a, b) {
+                Some(link) if link.alive() => *link,
+                _ => Link::new(a, b, now),
+            };
+            index.insert(key, links.len());
+            links.push(link);
+        }
+
+        let mut deaths = vec![];
+        for link in self.links.iter() {
+            if index.contains_key(&link.key()) {
+                continue;
+            }
+            let link = match link.until {
+                Some(_) => *link,
+                None => {
+                    let link = Link {
+                        until: Some(now),
+                        ..*link
+                    };
+                    deaths.push(link);
+                    link
+                }
+            };
+            if link.dead_for(now).unwrap() <= self.fade_out {
+                index.insert(link.key(), links.len());
+                links.push(link);
+            }
+        }
+
+        self.links = links;
+        self.index = index;
+        deaths
+    }
+
+    /// Drops the links `keep` rejects, like the ones to removed particles.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Link) -> bool) {
+        self.links.retain(|link| keep(link));
+        self.index = self
+            .links
+            .iter()
+            .enumerate()
+            .map(|(i, link)| (link.key(), i))
+            .collect();
+    }
+
+    pub fn get(&self, a: usize, b: usize) -> Option<&Link> {
+        self.index.get(&key(a, b)).map(|&i| &self.links[i])
+    }
+
+    /// Alive and fading links.
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.links.iter()
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = &Link> {
+        self.links.iter().filter(|link| link.alive())
+    }
+
+    /// `Link::fade_out` with the tracker's fade-out time.
+    pub fn fade_out(&self, link: &Link, now: f64) -> f32 {
+        link.fade_out(now, self.fade_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_is_unordered() {
+        assert_eq!(key(3, 7), (3, 7));
+        assert_eq!(key(7, 3), (3, 7));
+        assert_eq!(Link::new(7, 3, 0.).key(), (3, 7));
+        assert!(Link::new(7, 3, 0.).connects(3, 7));
+    }
+
+    #[test]
+    fn tracker_keeps_links_across_updates() {
+        let mut tracker = LinkTracker::new(1.);
+        tracker.update([(0, 1), (1, 2)], 0.);
+        // The same pair the other way around is the same link.
+        let deaths = tracker.update([(1, 0), (2, 1), (1, 0)], 0.5);
+        assert!(deaths.is_empty());
+        assert_eq!(tracker.iter().count(), 2);
+        assert_eq!(tracker.get(0, 1).unwrap().since, 0.);
+        assert_eq!(tracker.get(2, 1).unwrap().since, 0.);
+    }
+
+    #[test]
+    fn tracker_fades_out_dead_links() {
+        let mut tracker = LinkTracker::new(1.);
+        tracker.update([(0, 1), (1, 2)], 0.);
+        let deaths = tracker.update([(0, 1)], 1.);
+        assert_eq!(deaths.len(), 1);
+        assert_eq!(deaths[0].key(), (1, 2));
+        assert_eq!(tracker.alive().count(), 1);
+        assert_eq!(tracker.get(1, 2).unwrap().until, Some(1.));
+        // Still fading, then gone.
+        assert!(tracker.update([(0, 1)], 1.5).is_empty());
+        assert!(tracker.get(1, 2).is_some());
+        tracker.update([(0, 1)], 2.5);
+        assert!(tracker.get(1, 2).is_none());
+    }
+
+    #[test]
+    fn revived_links_start_over() {
+        let mut tracker = LinkTracker::new(1.);
+        tracker.update([(0, 1)], 0.);
+        tracker.update([], 1.);
+        tracker.update([(1, 0)], 1.5);
+        let link = tracker.get(0, 1).unwrap();
+        assert!(link.alive());
+        assert_eq!(link.since, 1.5);
+    }
+
+    #[test]
+    fn retain_updates_the_index() {
+        let mut tracker = LinkTracker::new(1.);
+        tracker.update([(0, 1), (1, 2), (2, 3)], 0.);
+        tracker.retain(|link| link.a < 2 && link.b < 2);
+        assert_eq!(tracker.iter().count(), 1);
+        assert!(tracker.get(1, 0).is_some());
+        assert!(tracker.get(2, 3).is_none());
+    }
+}
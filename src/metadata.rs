@@ -0,0 +1,272 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// What produced a capture. Embedded into PNGs as text chunks so
+/// `reproduce` can relaunch the sketch from the picture alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub sketch: String,
+    /// `git describe` of the tree the sketch was built from, `-dirty` when
+    /// it had changes.
+    pub revision: String,
+    pub seed: u64,
+    pub step: u64,
+    /// The sketch's parameter file contents, for sketches that have one.
+    pub params: Option<String>,
+}
+
+impl Metadata {
+    pub fn new(sketch: impl Into<String>, seed: u64, step: u64, params: Option<String>) -> Self {
+        Metadata {
+            sketch: sketch.into(),
+            revision: revision(),
+            seed,
+            step,
+            params,
+        }
+    }
+
+    pub fn to_text(&self) -> Vec<(&'static str, String)> {
+        let mut text = vec![
+            ("Software", "rustyart".to_owned()),
+            ("sketch", self.sketch.clone()),
+            ("revision", self.revision.clone()),
+            ("seed", self.seed.to_string()),
+            ("step", self.step.to_string()),
+        ];
+        if let Some(params) = &self.params {
+            text.push(("params", params.clone()));
+        }
+        text
+    }
+
+    /// `None` for PNGs without a sketch and seed.
+    pub fn from_text(text: &[(String, String)]) -> Option<Self> {
+        let get = |key: &str| {
+            text.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.clone())
+        };
+        Some(Metadata {
+            sketch: get("sketch")?,
+            revision: get("revision").unwrap_or_default(),
+            seed: get("seed")?.parse().ok()?,
+            step: get("step").and_then(|step| step.parse().ok()).unwrap_or(0),
+            params: get("params"),
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Ok(Self::from_text(&read_text(&fs::read(path)?)?))
+    }
+
+    /// Adds the metadata to an existing PNG.
+    pub fn embed(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let png = insert_text(&fs::read(path)?, &self.to_text())?;
+        let temporary = path.with_extension("png.tmp");
+        fs::write(&temporary, png)?;
+        fs::rename(temporary, path)
+    }
+
+    /// `embed` once `path` is completely written, for captures that are
+    /// saved in the background like nannou's `capture_frame`.
+    pub fn embed_when_written(self, path: PathBuf) {
+        thread::spawn(move || {
+            let start = Instant::now();
+            while !is_complete(&path) {
+                if start.elapsed() > Duration::from_secs(10) {
+                    eprintln!("{} was never written", path.display());
+                    return;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            if let Err(error) = self.embed(&path) {
+                eprintln!("failed to embed metadata in {}: {}", path.display(), error);
+            }
+        });
+    }
+}
+
+/// `git describe --always --dirty` of the crate when it was built, or
+/// `unknown` outside git, from the build script.
+pub fn revision() -> String {
+    env!("RUSTYART_REVISION").to_owned()
+}
+
+fn is_complete(path: &Path) -> bool {
+    match fs::read(path) {
+        Ok(png) => chunks(&png)
+            .is_ok_and(|chunks| chunks.last().is_some_and(|chunk| &chunk.kind == b"IEND")),
+        Err(_) => false,
+    }
+}
+
+struct Chunk<'a> {
+    kind: [u8; 4],
+    data: &'a [u8],
+    /// The whole chunk including length and checksum.
+    raw: &'a [u8],
+}
+
+fn chunks(png: &[u8]) -> io::Result<Vec<Chunk<'_>>> {
+    if png.len() < 8 || png[..8] != SIGNATURE {
+        return Err(invalid("not a png"));
+    }
+    let mut chunks = vec![];
+    let mut rest = &png[8..];
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if rest.len() < length + 12 {
+            return Err(invalid("truncated chunk"));
+        }
+        chunks.push(Chunk {
+            kind: [rest[4], rest[5], rest[6], rest[7]],
+            data: &rest[8..8 + length],
+            raw: &rest[..length + 12],
+        });
+        rest = &rest[length + 12..];
+    }
+    Ok(chunks)
+}
+
+/// The `tEXt` and uncompressed `iTXt` entries of a PNG.
+pub fn read_text(png: &[u8]) -> io::Result<Vec<(String, String)>> {
+    let mut text = vec![];
+    for chunk in chunks(png)? {
+        let mut parts = chunk.data.splitn(2, |&byte| byte == 0);
+        let keyword = String::from_utf8_lossy(parts.next().unwrap_or_default()).into_owned();
+        let rest = parts.next().unwrap_or_default();
+        match &chunk.kind {
+            b"tEXt" => {
+                text.push((keyword, rest.iter().map(|&byte| byte as char).collect()));
+            }
+            // Compression flag and method, then language and translated
+            // keyword, both null terminated.
+            b"iTXt" if rest.len() >= 2 && rest[0] == 0 => {
+                let value = rest[2..]
+                    .splitn(3, |&byte| byte == 0)
+                    .nth(2)
+                    .unwrap_or_default();
+                text.push((keyword, String::from_utf8_lossy(value).into_owned()));
+            }
+            _ => (),
+        }
+    }
+    Ok(text)
+}
+
+/// `png` with an `iTXt` chunk per entry before its image data.
+pub fn insert_text(png: &[u8], text: &[(&str, String)]) -> io::Result<Vec<u8>> {
+    let chunks = chunks(png)?;
+    let mut out = SIGNATURE.to_vec();
+    let mut inserted = false;
+    for chunk in chunks.iter() {
+        if !inserted && (&chunk.kind == b"IDAT" || &chunk.kind == b"IEND") {
+            for (keyword, value) in text {
+                let mut data = keyword.as_bytes().to_vec();
+                data.extend_from_slice(&[0, 0, 0, 0, 0]);
+                data.extend_from_slice(value.as_bytes());
+                write_chunk(&mut out, b"iTXt", &data);
+            }
+            inserted = true;
+        }
+        out.extend_from_slice(chunk.raw);
+    }
+    Ok(out)
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let mut crc = crc32(kind, 0xffff_ffff);
+    crc = crc32(data, crc);
+    out.extend_from_slice(&(crc ^ 0xffff_ffff).to_be_bytes());
+}
+
+fn crc32(bytes: &[u8], mut crc: u32) -> u32 {
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PNG skeleton, enough for the chunk handling.
+    fn png() -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        write_chunk(&mut png, b"IDAT", &[1, 2, 3]);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789", 0xffff_ffff) ^ 0xffff_ffff, 0xcbf4_3926);
+        let mut iend = vec![];
+        write_chunk(&mut iend, b"IEND", &[]);
+        assert_eq!(iend[8..], [0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let metadata = Metadata {
+            sketch: "2023-12-03-14-31-35".to_owned(),
+            revision: "v1-dirty".to_owned(),
+            seed: 42,
+            step: 600,
+            params: Some("hue = 41.0\nname = 'ünïcode'\n".to_owned()),
+        };
+        let png = insert_text(&png(), &metadata.to_text()).unwrap();
+        let text = read_text(&png).unwrap();
+        assert_eq!(text[0], ("Software".to_owned(), "rustyart".to_owned()));
+        assert_eq!(Metadata::from_text(&text), Some(metadata));
+    }
+
+    #[test]
+    fn text_goes_before_the_image_data() {
+        let png = insert_text(&png(), &[("seed", "1".to_owned())]).unwrap();
+        let kinds = chunks(&png)
+            .unwrap()
+            .iter()
+            .map(|chunk| chunk.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [*b"IHDR", *b"iTXt", *b"IDAT", *b"IEND"]);
+    }
+
+    #[test]
+    fn from_text_needs_sketch_and_seed() {
+        let text = [("sketch".to_owned(), "a".to_owned())];
+        assert_eq!(Metadata::from_text(&text), None);
+        assert_eq!(Metadata::from_text(&[]), None);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_png() {
+        assert!(read_text(b"not a png").is_err());
+        let mut truncated = png();
+        truncated.truncate(20);
+        assert!(read_text(&truncated).is_err());
+    }
+}
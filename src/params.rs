@@ -0,0 +1,414 @@
+use crate::args::Args;
+use crate::gui::Ui;
+use crate::presets::Lerp;
+use nannou::color::{self, Gradient, Hsla, Mix, Rgba};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The tunables most particle sketches share. Lengths are in sketch units,
+/// times in simulation seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+    pub particle_radius: f32,
+    pub particle_number: usize,
+    pub particle_speed: f32,
+    pub particle_target_radius: f32,
+    pub particle_target_time: f32,
+    pub particle_distance_max: f32,
+    pub line_weight: f32,
+    /// sRGB red, green, blue, alpha. Drawn over every frame, the alpha sets
+    /// how fast trails fade.
+    pub background: [f32; 4],
+    pub gradient: Vec<Stop>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stop {
+    pub position: f32,
+    /// In degrees.
+    pub hue: f32,
+    pub saturation: f32,
+    pub lightness: f32,
+    #[serde(default = "opaque")]
+    pub alpha: f32,
+}
+
+fn opaque() -> f32 {
+    1.
+}
+
+impl Params {
+    pub fn background(&self) -> Rgba {
+        let [red, green, blue, alpha] = self.background;
+        color::rgba(red, green, blue, alpha)
+    }
+
+    pub fn gradient_stops(&self) -> Vec<(f32, Hsla)> {
+        self.gradient
+            .iter()
+            .map(|stop| (stop.position, stop.color()))
+            .collect()
+    }
+
+    pub fn gradient(&self) -> Gradient<Hsla> {
+        Gradient::with_domain(self.gradient_stops())
+    }
+
+    /// Sliders for every parameter and an editor for the gradient stops.
+    /// True if anything changed.
+    pub fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        changed |= ui.slider("radius", &mut self.particle_radius, 1.0..=300.);
+        changed |= ui.count("particles", &mut self.particle_number, 2..=2000);
+        changed |= ui.slider("speed", &mut self.particle_speed, 0.0..=5.);
+        changed |= ui.slider(
+            "target radius",
+            &mut self.particle_target_radius,
+            0.0..=2000.,
+        );
+        changed |= ui.slider("target time", &mut self.particle_target_time, 0.0..=60.);
+        changed |= ui.slider("distance max", &mut self.particle_distance_max, 0.0..=2000.);
+        changed |= ui.slider("line weight", &mut self.line_weight, 0.0..=50.);
+        changed |= ui.slider("background alpha", &mut self.background[3], 0.0..=0.2);
+        for i in 0..self.gradient.len() {
+            // Stops stay between their neighbours, so they never cross.
+            let low = i.checked_sub(1).map_or(0., |j| self.gradient[j].position);
+            let high = self.gradient.get(i + 1).map_or(1., |stop| stop.position);
+            changed |= self.gradient[i].edit(ui, &format!("stop {}", i + 1), low..=high);
+        }
+        if ui.button("add stop") {
+            let last = *self.gradient.last().expect("gradient without stops");
+            self.gradient.push(Stop {
+                position: 1.,
+                ..last
+            });
+            changed = true;
+        }
+        if self.gradient.len() > 1 && ui.button("remove stop") {
+            self.gradient.pop();
+            changed = true;
+        }
+        changed
+    }
+}
+
+impl Validate for Params {
+    fn validate(mut self) -> Result<Self, String> {
+        if self.gradient.is_empty() {
+            return Err("gradient needs at least one stop".to_owned());
+        }
+        if self.gradient.iter().any(|stop| !stop.position.is_finite()) {
+            return Err("gradient stop positions must be numbers".to_owned());
+        }
+        if self.particle_number < 2 {
+            return Err("particle_number must be at least 2".to_owned());
+        }
+        for stop in self.gradient.iter_mut() {
+            stop.position = stop.position.clamp(0., 1.);
+        }
+        self.gradient
+            .sort_by(|a, b| a.position.total_cmp(&b.position));
+        Ok(self)
+    }
+}
+
+impl Lerp for Params {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Params {
+            particle_radius: self.particle_radius.lerp(&to.particle_radius, t),
+            particle_number: self.particle_number.lerp(&to.particle_number, t),
+            particle_speed: self.particle_speed.lerp(&to.particle_speed, t),
+            particle_target_radius: self
+                .particle_target_radius
+                .lerp(&to.particle_target_radius, t),
+            particle_target_time: self.particle_target_time.lerp(&to.particle_target_time, t),
+            particle_distance_max: self
+                .particle_distance_max
+                .lerp(&to.particle_distance_max, t),
+            line_weight: self.line_weight.lerp(&to.line_weight, t),
+            background: self.background.lerp(&to.background, t),
+            gradient: self.gradient.lerp(&to.gradient, t),
+        }
+    }
+}
+
+/// Blends the colours of both gradients at the positions of all their stops,
+/// so a blend starts out exactly like `self` and ends exactly like `to`.
+impl Lerp for Vec<Stop> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let from_gradient = Gradient::with_domain(
+            self.iter()
+                .map(|stop| (stop.position, stop.color()))
+                .collect(),
+        );
+        let to_gradient = Gradient::with_domain(
+            to.iter()
+                .map(|stop| (stop.position, stop.color()))
+                .collect(),
+        );
+        let mut positions = self
+            .iter()
+            .chain(to)
+            .map(|stop| stop.position)
+            .collect::<Vec<f32>>();
+        positions.sort_by(f32::total_cmp);
+        positions.dedup();
+        positions
+            .into_iter()
+            .map(|position| {
+                let color = from_gradient
+                    .get(position)
+                    .mix(&to_gradient.get(position), t);
+                Stop {
+                    position,
+                    hue: color.hue.to_positive_degrees(),
+                    saturation: color.saturation,
+                    lightness: color.lightness,
+                    alpha: color.alpha,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Stop {
+    /// An opaque stop, `hue` in degrees.
+    pub fn new(position: f32, hue: f32, saturation: f32, lightness: f32) -> Self {
+        Stop {
+            position,
+            hue,
+            saturation,
+            lightness,
+            alpha: 1.,
+        }
+    }
+
+    pub fn color(&self) -> Hsla {
+        color::hsla(self.hue / 360., self.saturation, self.lightness, self.alpha)
+    }
+
+    /// A swatch with a slider for the position within `positions` and
+    /// colour sliders below it.
+    pub fn edit(&mut self, ui: &mut Ui, label: &str, positions: RangeInclusive<f32>) -> bool {
+        ui.swatch(label, self.color());
+        let mut changed = false;
+        changed |= ui.slider("  position", &mut self.position, positions);
+        changed |= ui.slider("  hue", &mut self.hue, 0.0..=360.);
+        changed |= ui.slider("  saturation", &mut self.saturation, 0.0..=1.);
+        changed |= ui.slider("  lightness", &mut self.lightness, 0.0..=1.);
+        changed
+    }
+}
+
+/// Parameters checked after they are read from a file.
+pub trait Validate: Sized {
+    /// The parameters with what can be repaired repaired, like gradient
+    /// stops out of order, or why they can't be used.
+    fn validate(self) -> Result<Self, String>;
+}
+
+/// A TOML file of parameters that is watched while the sketch runs. Keys
+/// missing from the file keep the sketch's defaults, so a file only needs
+/// what differs.
+#[derive(Clone, Debug)]
+pub struct ParamsFile<P> {
+    pub path: PathBuf,
+    defaults: P,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl<P: Clone + Serialize + DeserializeOwned + Validate> ParamsFile<P> {
+    /// How often `poll` looks at the file.
+    pub const INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(path: impl Into<PathBuf>, defaults: P) -> Self {
+        ParamsFile {
+            path: path.into(),
+            defaults,
+            modified: None,
+            checked: Instant::now(),
+        }
+    }
+
+    /// The `--params` flag, or the `.toml` next to the sketch's source, given
+    /// as `file!()`.
+    pub fn for_sketch(source: &str, defaults: P) -> Self {
+        let path = Args::parse().params.unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join(source)
+                .with_extension("toml")
+        });
+        Self::new(path, defaults)
+    }
+
+    /// Reads the file, writing the defaults to it first if there is none
+    /// yet. Falls back to the defaults if it doesn't parse or validate.
+    pub fn load(&mut self) -> P {
+        if !self.path.exists() {
+            let defaults = to_toml(&self.defaults).expect("params are not toml");
+            if let Err(error) = fs::write(&self.path, defaults) {
+                eprintln!("failed to write {}: {}", self.path.display(), error);
+            }
+        }
+        self.modified = self.modified_time();
+        self.read().unwrap_or_else(|error| {
+            eprintln!("{}: {}", self.path.display(), error);
+            self.defaults.clone()
+        })
+    }
+
+    /// The new parameters if the file changed since it was last read. Errors
+    /// are printed and the old parameters stay.
+    pub fn poll(&mut self) -> Option<P> {
+        if self.checked.elapsed() < Self::INTERVAL {
+            return None;
+        }
+        self.checked = Instant::now();
+        let modified = self.modified_time();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match self.read() {
+            Ok(params) => {
+                println!("reloaded {}", self.path.display());
+                Some(params)
+            }
+            Err(error) => {
+                eprintln!("{}: {}", self.path.display(), error);
+                None
+            }
+        }
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    fn read(&self) -> Result<P, String> {
+        let text = fs::read_to_string(&self.path).map_err(|error| error.to_string())?;
+        let file: toml::Value = text
+            .parse()
+            .map_err(|error: toml::de::Error| error.to_string())?;
+        let mut merged =
+            toml::Value::try_from(&self.defaults).map_err(|error| error.to_string())?;
+        merge(&mut merged, file);
+        let params: P = merged.try_into().map_err(|error| error.to_string())?;
+        params.validate()
+    }
+}
+
+/// `value` as pretty TOML. Goes through a `toml::Value`, which puts tables
+/// after plain keys, so parameters that flatten another struct before their
+/// own keys still serialize. Floats are written as the `f32` they came from.
+pub(crate) fn to_toml(value: &impl Serialize) -> Result<String, toml::ser::Error> {
+    let mut value = toml::Value::try_from(value)?;
+    shorten_floats(&mut value);
+    toml::to_string_pretty(&value)
+}
+
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) if (*float as f32) as f64 == *float => {
+            *float = (*float as f32).to_string().parse().unwrap_or(*float);
+        }
+        toml::Value::Array(array) => array.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| shorten_floats(value)),
+        _ => (),
+    }
+}
+
+/// Overlays `value` onto `base`, tables key by key, anything else whole.
+pub(crate) fn merge(base: &mut toml::Value, value: toml::Value) {
+    match (base, value) {
+        (toml::Value::Table(base), toml::Value::Table(table)) => {
+            for (key, value) in table {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Params {
+        Params {
+            particle_radius: 70.,
+            particle_number: 250,
+            particle_speed: 0.7,
+            particle_target_radius: 420.,
+            particle_target_time: 12.,
+            particle_distance_max: 500.,
+            line_weight: 13.,
+            background: [0., 0., 0., 0.018],
+            gradient: vec![Stop::new(0., 41., 1., 0.5), Stop::new(1., 234., 1., 0.5)],
+        }
+    }
+
+    #[test]
+    fn merge_overlays_tables_key_by_key() {
+        let mut base: toml::Value = "a = 1\n[table]\nb = 2\nc = 3\n".parse().unwrap();
+        let file: toml::Value = "d = [4]\n[table]\nc = 5\n".parse().unwrap();
+        merge(&mut base, file);
+        let expected: toml::Value = "a = 1\nd = [4]\n[table]\nb = 2\nc = 5\n".parse().unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn merge_replaces_arrays_whole() {
+        let mut base: toml::Value = "a = [1, 2, 3]".parse().unwrap();
+        merge(&mut base, "a = [4]".parse().unwrap());
+        assert_eq!(base, "a = [4]".parse().unwrap());
+    }
+
+    #[test]
+    fn to_toml_round_trips_with_short_floats() {
+        let text = to_toml(&params()).unwrap();
+        assert!(text.contains("particle_speed = 0.7\n"), "{}", text);
+        assert_eq!(toml::from_str::<Params>(&text).unwrap(), params());
+    }
+
+    #[test]
+    fn validate_sorts_and_clamps_stops() {
+        let mut params = params();
+        params.gradient[0].position = 2.;
+        params.gradient[1].position = 0.5;
+        let params = params.validate().unwrap();
+        let positions = params
+            .gradient
+            .iter()
+            .map(|stop| stop.position)
+            .collect::<Vec<f32>>();
+        assert_eq!(positions, [0.5, 1.]);
+    }
+
+    #[test]
+    fn validate_rejects_unusable_params() {
+        let mut empty = params();
+        empty.gradient.clear();
+        assert!(empty.validate().is_err());
+        let mut nan = params();
+        nan.gradient[0].position = f32::NAN;
+        assert!(nan.validate().is_err());
+        let mut alone = params();
+        alone.particle_number = 1;
+        assert!(alone.validate().is_err());
+    }
+}
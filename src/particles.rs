@@ -0,0 +1,85 @@
+use nannou::prelude::*;
+use ordered_float::OrderedFloat;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position: Vec2,
+    pub radius: f32,
+    pub target: Vec2,
+    pub target_since: f64,
+}
+
+impl Particle {
+    /// `now` is the simulation time, see `clock::Clock`.
+    pub fn new(position: Vec2, radius: f32, target: Vec2, now: f64) -> Self {
+        Particle {
+            position,
+            radius,
+            target,
+            target_since: now,
+        }
+    }
+}
+
+/// Sorts `others` by distance to `self`, closest first. When `self` is part of
+/// `others` it ends up at index 0, so its nearest neighbour is at index 1.
+/// This sorts everything on every call, use `spatial::Grid` for neighbour
+/// queries on more than a few hundred particles.
+pub trait RankeableByDistance {
+    fn rank_by_distance(&self, others: &[Self]) -> Vec<Self>
+    where
+        Self: Sized;
+}
+
+impl RankeableByDistance for Particle {
+    fn rank_by_distance(&self, others: &[Self]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let mut ranking = others.to_vec();
+        ranking
+            .sort_by_cached_key(|particle| OrderedFloat(particle.position.distance(self.position)));
+        ranking
+    }
+}
+
+impl RankeableByDistance for Vec2 {
+    fn rank_by_distance(&self, others: &[Self]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let mut ranking = others.to_vec();
+        ranking.sort_by_cached_key(|vec| OrderedFloat(vec.distance(*self)));
+        ranking
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_closest_first() {
+        let points = [vec2(5., 0.), vec2(0., 1.), vec2(-3., 0.), vec2(0., 0.)];
+        let ranking = vec2(0., 0.).rank_by_distance(&points);
+        assert_eq!(
+            ranking,
+            [vec2(0., 0.), vec2(0., 1.), vec2(-3., 0.), vec2(5., 0.)]
+        );
+    }
+
+    #[test]
+    fn particle_ranks_itself_first() {
+        let particles = [0., 3., 1., 2.]
+            .map(|x| Particle::new(vec2(x, 0.), 1., Vec2::ZERO, 0.))
+            .to_vec();
+        let ranking = particles[2].rank_by_distance(&particles);
+        let xs = ranking
+            .iter()
+            .map(|particle| particle.position.x)
+            .collect::<Vec<f32>>();
+        assert_eq!(xs[0], 1.);
+        assert!(xs[1] == 0. || xs[1] == 2.);
+        assert_eq!(xs[3], 3.);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::render::Canvas;
+use crate::spatial::Grid;
+use nannou::color::{IntoLinSrgba, LinSrgba};
+use nannou::geom::Rect;
+use nannou::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Stroke {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub color: LinSrgba,
+}
+
+/// Records the lines a sketch draws, clipped to the view, for plotting.
+/// Everything else is ignored. Drawing several frames into the same `Plot`
+/// accumulates their lines, `background` starts over.
+#[derive(Clone, Debug)]
+pub struct Plot {
+    view: Rect,
+    strokes: Vec<Stroke>,
+    limit: usize,
+}
+
+impl Plot {
+    pub fn new(view: Rect) -> Self {
+        Self::with_limit(view, usize::MAX)
+    }
+
+    /// Records at most `limit` strokes and ignores the lines after them.
+    pub fn with_limit(view: Rect, limit: usize) -> Self {
+        Plot {
+            view,
+            strokes: vec![],
+            limit,
+        }
+    }
+
+    pub fn strokes(&self) -> &[Stroke] {
+        &self.strokes
+    }
+
+    pub fn full(&self) -> bool {
+        self.strokes.len() >= self.limit
+    }
+}
+
+impl Canvas for Plot {
+    fn bounds(&self) -> Rect {
+        self.view
+    }
+
+    fn background(&mut self, _color: impl IntoLinSrgba<f32>) {
+        self.strokes.clear();
+    }
+
+    fn rect(&mut self, _rect: Rect, _color: impl IntoLinSrgba<f32>) {}
+
+    fn line(&mut self, start: Vec2, end: Vec2, _weight: f32, color: impl IntoLinSrgba<f32>) {
+        if self.full() {
+            return;
+        }
+        if let Some((start, end)) = clip(start, end, self.view) {
+            self.strokes.push(Stroke {
+                start,
+                end,
+                color: color.into_lin_srgba(),
+            });
+        }
+    }
+
+    fn ellipse(&mut self, _center: Vec2, _radius: f32, _color: impl IntoLinSrgba<f32>) {}
+
+    fn ellipse_outline(
+        &mut self,
+        _center: Vec2,
+        _radius: f32,
+        _weight: f32,
+        _color: impl IntoLinSrgba<f32>,
+    ) {
+    }
+
+    fn polygon(&mut self, _points: &[Vec2], _color: impl IntoLinSrgba<f32>) {}
+
+    fn region(&mut self, _rings: &[Vec<Vec2>], _color: impl IntoLinSrgba<f32>) {}
+
+    fn triangle(&mut self, _corners: [Vec2; 3], _colors: [LinSrgba; 3]) {}
+}
+
+/// Paper size and margin in millimetres.
+#[derive(Copy, Clone, Debug)]
+pub struct Paper {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+}
+
+impl Paper {
+    pub const A4: Paper = Paper {
+        width: 297.,
+        height: 210.,
+        margin: 15.,
+    };
+    pub const A3: Paper = Paper {
+        width: 420.,
+        height: 297.,
+        margin: 20.,
+    };
+    pub const LETTER: Paper = Paper {
+        width: 279.4,
+        height: 215.9,
+        margin: 15.,
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct PlotSettings {
+    pub paper: Paper,
+    /// Every stroke is plotted with the pen of the closest colour.
+    pub pens: Vec<LinSrgba>,
+    /// Strokes more transparent than this are left out.
+    pub min_alpha: f32,
+    /// Endpoints closer than this many millimetres are merged.
+    pub tolerance: f32,
+    /// G-code feed rate in mm/min.
+    pub feed_rate: f32,
+    /// G-code Z height of the lifted and lowered pen.
+    pub pen_up: f32,
+    pub pen_down: f32,
+}
+
+impl Default for PlotSettings {
+    fn default() -> Self {
+        PlotSettings {
+            paper: Paper::A3,
+            pens: vec![LinSrgba::new(0., 0., 0., 1.)],
+            min_alpha: 0.05,
+            tolerance: 0.1,
+            feed_rate: 3000.,
+            pen_up: 5.,
+            pen_down: 0.,
+        }
+    }
+}
+
+/// Polylines in millimetres for one pen, in plotting order.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub pen: usize,
+    pub paths: Vec<Vec<Vec2>>,
+}
+
+impl Layer {
+    /// Pen-up distance from the paper origin through all paths.
+    pub fn travel(&self) -> f32 {
+        let mut position = Vec2::ZERO;
+        let mut travel = 0.;
+        for path in self.paths.iter() {
+            travel += position.distance(path[0]);
+            position = *path.last().unwrap();
+        }
+        travel
+    }
+}
+
+/// Scales the plot into the printable area of the paper, sorts the strokes
+/// onto pens, joins them into polylines and orders those to keep pen-up
+/// travel short.
+pub fn layers(plot: &Plot, settings: &PlotSettings) -> Vec<Layer> {
+    let paper = settings.paper;
+    let printable = vec2(paper.width, paper.height) - 2. * paper.margin;
+    let scale = (printable.x / plot.view.w()).min(printable.y / plot.view.h());
+    let offset = Vec2::splat(paper.margin) + (printable - plot.view.wh() * scale) / 2.;
+    let to_paper = |point: Vec2| offset + (point - plot.view.bottom_left()) * scale;
+
+    let mut segments = vec![vec![]; settings.pens.len()];
+    for stroke in plot.strokes.iter() {
+        if stroke.color.alpha < settings.min_alpha {
+            continue;
+        }
+        segments[closest_pen(stroke.color, &settings.pens)]
+            .push((to_paper(stroke.start), to_paper(stroke.end)));
+    }
+
+    segments
+        .into_iter()
+        .enumerate()
+        .filter(|(_, segments)| !segments.is_empty())
+        .map(|(pen, segments)| Layer {
+            pen,
+            paths: order(join(&segments, settings.tolerance)),
+        })
+        .collect()
+}
+
+fn closest_pen(color: LinSrgba, pens: &[LinSrgba]) -> usize {
+    let distance = |pen: &LinSrgba| {
+        (pen.red - color.red).powi(2)
+            + (pen.green - color.green).powi(2)
+            + (pen.blue - color.blue).powi(2)
+    };
+    (0..pens.len())
+        .min_by(|&a, &b| distance(&pens[a]).total_cmp(&distance(&pens[b])))
+        .unwrap_or(0)
+}
+
+/// Merges endpoints closer than `tolerance` and walks the resulting graph
+/// into as few polylines as a greedy walk finds, starting at odd nodes so
+/// open chains are not split. Repeated segments are plotted once.
+pub fn join(segments: &[(Vec2, Vec2)], tolerance: f32) -> Vec<Vec<Vec2>> {
+    let mut nodes: Vec<Vec2> = vec![];
+    let mut node_keys = HashMap::new();
+    let mut node = |point: Vec2| {
+        let key = (
+            (point.x / tolerance).round() as i64,
+            (point.y / tolerance).round() as i64,
+        );
+        *node_keys.entry(key).or_insert_with(|| {
+            nodes.push(point);
+            nodes.len() - 1
+        })
+    };
+
+    let mut edges = vec![];
+    let mut seen = HashSet::new();
+    for (start, end) in segments.iter() {
+        let (a, b) = (node(*start), node(*end));
+        if a != b && seen.insert((a.min(b), a.max(b))) {
+            edges.push((a, b));
+        }
+    }
+
+    let mut adjacent = vec![vec![]; nodes.len()];
+    for (i, (a, b)) in edges.iter().enumerate() {
+        adjacent[*a].push(i);
+        adjacent[*b].push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut starts = (0..nodes.len())
+        .filter(|&node| adjacent[node].len() % 2 == 1)
+        .collect::<Vec<usize>>();
+    starts.extend(0..nodes.len());
+
+    let mut paths = vec![];
+    for start in starts {
+        while let Some(&first) = adjacent[start].iter().find(|&&edge| !used[edge]) {
+            let mut path = vec![nodes[start]];
+            let mut current = start;
+            let mut edge = Some(first);
+            while let Some(i) = edge {
+                used[i] = true;
+                let (a, b) = edges[i];
+                current = if a == current { b } else { a };
+                path.push(nodes[current]);
+                edge = adjacent[current].iter().copied().find(|&edge| !used[edge]);
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Greedy nearest neighbour ordering from the paper origin. Paths are
+/// reversed when their end is closer than their start.
+pub fn order(paths: Vec<Vec<Vec2>>) -> Vec<Vec<Vec2>> {
+    // endpoint 2 * i is the start of path i, 2 * i + 1 its end
+    let grid = Grid::new(
+        paths
+            .iter()
+            .flat_map(|path| [path[0], *path.last().unwrap()]),
+    );
+    let mut paths = paths
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<Option<Vec<Vec2>>>>();
+    let mut ordered = vec![];
+    let mut position = Vec2::ZERO;
+    while let Some(&endpoint) = grid
+        .nearest_k_filtered(position, 1, |endpoint| paths[endpoint / 2].is_some())
+        .first()
+    {
+        let mut path = paths[endpoint / 2].take().unwrap();
+        if endpoint % 2 == 1 {
+            path.reverse();
+        }
+        position = *path.last().unwrap();
+        ordered.push(path);
+    }
+    ordered
+}
+
+/// HPGL with 40 plotter units per millimetre, one `SP` per layer.
+pub fn hpgl(layers: &[Layer]) -> String {
+    let unit = |point: &Vec2| format!("{},{}", (point.x * 40.).round(), (point.y * 40.).round());
+    let mut hpgl = String::from("IN;\n");
+    for layer in layers.iter() {
+        writeln!(hpgl, "SP{};", layer.pen + 1).unwrap();
+        for path in layer.paths.iter() {
+            writeln!(hpgl, "PU{};", unit(&path[0])).unwrap();
+            let points = path[1..].iter().map(unit).collect::<Vec<String>>();
+            writeln!(hpgl, "PD{};", points.join(",")).unwrap();
+        }
+        hpgl += "PU;\n";
+    }
+    hpgl += "SP0;\n";
+    hpgl
+}
+
+/// GRBL style G-code in millimetres, lifting the pen on Z. Pauses with `M0`
+/// between layers to change pens.
+pub fn gcode(layers: &[Layer], settings: &PlotSettings) -> String {
+    let mut gcode = String::from("G21\nG90\n");
+    writeln!(gcode, "G0 Z{}", settings.pen_up).unwrap();
+    for (i, layer) in layers.iter().enumerate() {
+        if i > 0 {
+            writeln!(gcode, "G0 X0 Y0\n; change to pen {}\nM0", layer.pen + 1).unwrap();
+        } else {
+            writeln!(gcode, "; pen {}", layer.pen + 1).unwrap();
+        }
+        for path in layer.paths.iter() {
+            writeln!(gcode, "G0 X{:.3} Y{:.3}", path[0].x, path[0].y).unwrap();
+            writeln!(gcode, "G1 Z{} F{}", settings.pen_down, settings.feed_rate).unwrap();
+            for point in path[1..].iter() {
+                writeln!(gcode, "G1 X{:.3} Y{:.3}", point.x, point.y).unwrap();
+            }
+            writeln!(gcode, "G0 Z{}", settings.pen_up).unwrap();
+        }
+    }
+    gcode += "G0 X0 Y0\nM2\n";
+    gcode
+}
+
+/// Liang-Barsky clipping of a segment to `rect`.
+pub fn clip(start: Vec2, end: Vec2, rect: Rect) -> Option<(Vec2, Vec2)> {
+    let delta = end - start;
+    let mut t0 = 0f32;
+    let mut t1 = 1f32;
+    for (p, q) in [
+        (-delta.x, start.x - rect.left()),
+        (delta.x, rect.right() - start.x),
+        (-delta.y, start.y - rect.bottom()),
+        (delta.y, rect.top() - start.y),
+    ] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0. {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some((start + delta * t0, start + delta * t1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_chains_segments_in_any_order() {
+        let segments = [
+            (vec2(2., 0.), vec2(1., 0.)),
+            (vec2(0., 0.), vec2(1., 0.)),
+            (vec2(2., 0.), vec2(3., 0.)),
+        ];
+        let paths = join(&segments, 0.01);
+        assert_eq!(paths.len(), 1);
+        let path = &paths[0];
+        assert_eq!(path.len(), 4);
+        let ends = [path[0], path[3]];
+        assert!(ends.contains(&vec2(0., 0.)) && ends.contains(&vec2(3., 0.)));
+    }
+
+    #[test]
+    fn join_closes_loops_and_drops_repeats() {
+        let square = [vec2(0., 0.), vec2(1., 0.), vec2(1., 1.), vec2(0., 1.)];
+        let mut segments = (0..4)
+            .map(|i| (square[i], square[(i + 1) % 4]))
+            .collect::<Vec<_>>();
+        segments.push((square[1], square[0]));
+        let paths = join(&segments, 0.01);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 5);
+        assert_eq!(paths[0][0], paths[0][4]);
+    }
+
+    #[test]
+    fn join_merges_close_endpoints() {
+        let segments = [
+            (vec2(0., 0.), vec2(1., 0.)),
+            (vec2(1.001, 0.), vec2(2., 0.)),
+        ];
+        assert_eq!(join(&segments, 0.01).len(), 1);
+        assert_eq!(join(&segments, 0.0001).len(), 2);
+    }
+
+    #[test]
+    fn order_walks_from_the_origin() {
+        let paths = vec![
+            vec![vec2(10., 0.), vec2(20., 0.)],
+            vec![vec2(3., 0.), vec2(1., 0.)],
+            vec![vec2(8., 0.), vec2(4., 0.)],
+        ];
+        let ordered = order(paths);
+        assert_eq!(
+            ordered,
+            [
+                vec![vec2(1., 0.), vec2(3., 0.)],
+                vec![vec2(4., 0.), vec2(8., 0.)],
+                vec![vec2(10., 0.), vec2(20., 0.)],
+            ]
+        );
+    }
+
+    #[test]
+    fn clip_to_rect() {
+        let rect = Rect::from_corners(vec2(0., 0.), vec2(10., 10.));
+        let inside = (vec2(1., 1.), vec2(9., 5.));
+        assert_eq!(clip(inside.0, inside.1, rect), Some(inside));
+        assert_eq!(
+            clip(vec2(-5., 5.), vec2(15., 5.), rect),
+            Some((vec2(0., 5.), vec2(10., 5.)))
+        );
+        assert_eq!(clip(vec2(-5., 5.), vec2(-1., 5.), rect), None);
+        assert_eq!(clip(vec2(-5., 20.), vec2(15., 20.), rect), None);
+        assert_eq!(clip(vec2(-5., 0.), vec2(5., 40.), rect), None);
+    }
+
+    #[test]
+    fn full_plot_ignores_lines() {
+        let mut plot = Plot::with_limit(Rect::from_w_h(100., 100.), 2);
+        for _ in 0..3 {
+            plot.line(vec2(0., 0.), vec2(10., 10.), 1., nannou::color::BLACK);
+        }
+        assert!(plot.full());
+        assert_eq!(plot.strokes().len(), 2);
+    }
+}
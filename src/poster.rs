@@ -0,0 +1,118 @@
+use crate::args::Args;
+use crate::render::Raster;
+use nannou::geom::Rect;
+use nannou::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+/// Renders a sketch far beyond the window size, like 12000x12000 for
+/// prints. The poster is cut into tiles, each tile replays the simulation
+/// from the same model with the camera offset onto it, so trails build up
+/// exactly like on screen. Tiles are stitched a row at a time, the whole
+/// poster never has to fit in memory.
+#[derive(Clone, Debug)]
+pub struct Poster {
+    pub path: PathBuf,
+    pub frames: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Pixels per sketch unit. Line weights are in sketch units, so they and
+    /// the background fade look the same as on screen, only sharper.
+    pub scale: f32,
+    pub tile: u32,
+}
+
+impl Poster {
+    /// The window the sketches are made for, fitted into the poster unless
+    /// `--scale` is given.
+    pub const WINDOW: (f32, f32) = (1920., 1080.);
+
+    /// Set up when `--poster` was given, 600 frames at 12000x12000 in 2048
+    /// pixel tiles by default.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let (width, height) = args.size.unwrap_or((12000, 12000));
+        let (window_width, window_height) = Self::WINDOW;
+        Some(Poster {
+            path: args.poster.clone()?,
+            frames: args.frames.unwrap_or(600),
+            width,
+            height,
+            scale: args.scale.unwrap_or_else(|| {
+                (width as f32 / window_width).min(height as f32 / window_height)
+            }),
+            tile: args.tile.unwrap_or(2048),
+        })
+    }
+
+    /// The part of the sketch the whole poster shows.
+    pub fn view(&self) -> Rect {
+        Rect::from_w_h(
+            self.width as f32 / self.scale,
+            self.height as f32 / self.scale,
+        )
+    }
+
+    /// The raster for the pixels `x`..`x + width`, `y`..`y + height` of the
+    /// poster, reporting the whole poster as its bounds.
+    pub fn tile_raster(&self, x: u32, y: u32, width: u32, height: u32) -> Raster {
+        let view = self.view();
+        let top_left = pt2(
+            view.left() + x as f32 / self.scale,
+            view.top() - y as f32 / self.scale,
+        );
+        let bottom_right = top_left + vec2(width as f32, -(height as f32)) / self.scale;
+        let mut raster = Raster::new(width, height, Rect::from_corners(top_left, bottom_right));
+        raster.set_bounds(view);
+        raster
+    }
+
+    /// Calls `frame` `frames` times per tile on a fresh clone of `model`,
+    /// then writes the stitched PNG.
+    pub fn run<M: Clone>(&self, model: &M, mut frame: impl FnMut(&mut M, &mut Raster)) {
+        self.write(|x, y, width, height| {
+            let mut raster = self.tile_raster(x, y, width, height);
+            let mut model = model.clone();
+            for _ in 0..self.frames {
+                frame(&mut model, &mut raster);
+            }
+            raster
+        })
+        .expect("failed to write poster");
+        println!("rendered {}", self.path.display());
+    }
+
+    fn write(&self, mut tile: impl FnMut(u32, u32, u32, u32) -> Raster) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer();
+
+        let columns = self.width.div_ceil(self.tile);
+        let rows = self.height.div_ceil(self.tile);
+        let stride = self.width as usize * 4;
+        for row in 0..rows {
+            let y = row * self.tile;
+            let height = self.tile.min(self.height - y);
+            let mut band = vec![0; stride * height as usize];
+            for column in 0..columns {
+                let x = column * self.tile;
+                let width = self.tile.min(self.width - x);
+                eprintln!("tile {}/{}", row * columns + column + 1, rows * columns);
+                let image = tile(x, y, width, height).to_image();
+                for (line, pixels) in image.as_raw().chunks(width as usize * 4).enumerate() {
+                    let start = line * stride + x as usize * 4;
+                    band[start..start + pixels.len()].copy_from_slice(pixels);
+                }
+            }
+            stream.write_all(&band)?;
+        }
+        stream.finish()?;
+        Ok(())
+    }
+}
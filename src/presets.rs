@@ -0,0 +1,424 @@
+use crate::params::{self, Validate};
+use nannou::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Parameters that can be blended, `t` going from 0 at `self` to 1 at `to`.
+pub trait Lerp {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for usize {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        (*self as f32).lerp(&(*to as f32), t).round() as usize
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let mut lerped = *self;
+        for (value, to) in lerped.iter_mut().zip(to) {
+            *value = value.lerp(to, t);
+        }
+        lerped
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Preset<P> {
+    pub name: String,
+    pub params: P,
+}
+
+/// Named parameter sets in `<sketch>.presets.toml`, switched with the number
+/// keys. Switching eases from the current parameters to the preset over
+/// `fade` seconds so nothing pops.
+#[derive(Clone, Debug)]
+pub struct Presets<P> {
+    pub path: PathBuf,
+    /// Indexed by slot, the number key minus one. Empty slots stay empty so
+    /// the others keep their keys.
+    pub presets: Vec<Option<Preset<P>>>,
+    pub fade: f32,
+    crossfade: Option<Crossfade<P>>,
+    naming: Option<Naming<P>>,
+}
+
+#[derive(Clone, Debug)]
+struct Crossfade<P> {
+    from: P,
+    to: P,
+    elapsed: f32,
+    name: String,
+}
+
+/// Parameters waiting for a name before they are stored in `index`.
+#[derive(Clone, Debug)]
+struct Naming<P> {
+    index: usize,
+    name: String,
+    params: P,
+}
+
+#[derive(Serialize)]
+struct File<'a, P> {
+    presets: Vec<Entry<'a, P>>,
+}
+
+/// A preset as saved, with its number key.
+#[derive(Serialize)]
+struct Entry<'a, P> {
+    slot: usize,
+    name: &'a str,
+    params: &'a P,
+}
+
+impl<P: Clone + Lerp + Serialize + DeserializeOwned + Validate> Presets<P> {
+    /// The presets next to the sketch's source, given as `file!()`. Keys a
+    /// preset leaves out come from `defaults`.
+    pub fn for_sketch(source: &str, defaults: &P) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(source)
+            .with_extension("presets.toml");
+        Self::load(path, defaults)
+    }
+
+    pub fn load(path: impl Into<PathBuf>, defaults: &P) -> Self {
+        let path = path.into();
+        let presets = match fs::read_to_string(&path) {
+            Ok(text) => read(&text, defaults).unwrap_or_else(|error| {
+                eprintln!("{}: {}", path.display(), error);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        Presets {
+            path,
+            presets,
+            fade: 3.,
+            crossfade: None,
+            naming: None,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let file = File {
+            presets: self
+                .presets
+                .iter()
+                .enumerate()
+                .filter_map(|(index, preset)| {
+                    let preset = preset.as_ref()?;
+                    Some(Entry {
+                        slot: index + 1,
+                        name: &preset.name,
+                        params: &preset.params,
+                    })
+                })
+                .collect(),
+        };
+        let text = params::to_toml(&file).expect("presets are not toml");
+        fs::write(&self.path, text)
+    }
+
+    /// Stores `params` as preset `index` called `name`.
+    pub fn store(&mut self, index: usize, name: &str, params: &P) {
+        if self.presets.len() <= index {
+            self.presets.resize_with(index + 1, || None);
+        }
+        self.presets[index] = Some(Preset {
+            name: name.to_owned(),
+            params: params.clone(),
+        });
+    }
+
+    /// Starts easing from `current` to preset `index`, if there is one.
+    pub fn switch(&mut self, index: usize, current: &P) -> Option<&str> {
+        let preset = self.presets.get(index)?.as_ref()?;
+        self.crossfade = Some(Crossfade {
+            from: current.clone(),
+            to: preset.params.clone(),
+            elapsed: 0.,
+            name: preset.name.clone(),
+        });
+        Some(&preset.name)
+    }
+
+    /// Number keys switch to presets 1 to 9. With shift held they take the
+    /// current parameters for that slot and ask for a name, typed through
+    /// `received_character`, which Return stores and saves. Returning an
+    /// empty name cancels. True if the key was used, which every key is
+    /// while a name is typed.
+    pub fn key_released(&mut self, app: &App, key: Key, current: &P) -> bool {
+        if let Some(naming) = self.naming.take() {
+            if key != Key::Return {
+                self.naming = Some(naming);
+                return true;
+            }
+            let name = naming.name.trim();
+            if name.is_empty() {
+                println!("preset {} not stored", naming.index + 1);
+                return true;
+            }
+            self.store(naming.index, name, &naming.params);
+            // A failed save must not end a performance, the preset still
+            // works until the sketch is closed.
+            match self.save() {
+                Ok(()) => println!(
+                    "saved {} as {} in {}",
+                    name,
+                    naming.index + 1,
+                    self.path.display()
+                ),
+                Err(error) => eprintln!("failed to save {}: {}", self.path.display(), error),
+            }
+            return true;
+        }
+        let index = match digit(key) {
+            Some(digit) => digit - 1,
+            None => return false,
+        };
+        if app.keys.mods.shift() {
+            let name = match self.presets.get(index) {
+                Some(Some(preset)) => preset.name.clone(),
+                _ => format!("preset {}", index + 1),
+            };
+            self.naming = Some(Naming {
+                index,
+                name,
+                params: current.clone(),
+            });
+        } else if let Some(name) = self.switch(index, current) {
+            println!("preset {}", name);
+        }
+        true
+    }
+
+    /// Types `character` into the name asked for by `key_released`,
+    /// backspace deletes. True if a name is being typed.
+    pub fn received_character(&mut self, character: char) -> bool {
+        let naming = match self.naming.as_mut() {
+            Some(naming) => naming,
+            None => return false,
+        };
+        match character {
+            '\u{8}' | '\u{7f}' => {
+                naming.name.pop();
+            }
+            character if !character.is_control() => naming.name.push(character),
+            _ => (),
+        }
+        true
+    }
+
+    /// The name being typed, or the preset being switched to, for
+    /// `Controls::status`.
+    pub fn status(&self) -> Option<String> {
+        if let Some(naming) = &self.naming {
+            return Some(format!(
+                "name preset {}: {}_  (return stores)",
+                naming.index + 1,
+                naming.name
+            ));
+        }
+        let crossfade = self.crossfade.as_ref()?;
+        Some(format!("preset {}", crossfade.name))
+    }
+
+    /// The parameters for this frame while switching, `elapsed` being real
+    /// time so fades take as long when the clock runs faster.
+    pub fn update(&mut self, elapsed: Duration) -> Option<P> {
+        let crossfade = self.crossfade.as_mut()?;
+        crossfade.elapsed += elapsed.as_secs_f32();
+        let t = (crossfade.elapsed / self.fade).min(1.);
+        if t >= 1. {
+            return self.crossfade.take().map(|crossfade| crossfade.to);
+        }
+        let eased = t * t * (3. - 2. * t);
+        Some(crossfade.from.lerp(&crossfade.to, eased))
+    }
+}
+
+/// The presets in `text` by slot. Presets without a slot, as in files from
+/// before slots, take the first free one.
+fn read<P: Serialize + DeserializeOwned + Validate>(
+    text: &str,
+    defaults: &P,
+) -> Result<Vec<Option<Preset<P>>>, String> {
+    let file: toml::Value = text
+        .parse()
+        .map_err(|error: toml::de::Error| error.to_string())?;
+    let defaults = toml::Value::try_from(defaults).map_err(|error| error.to_string())?;
+    let entries = match file.get("presets") {
+        Some(toml::Value::Array(presets)) => presets.clone(),
+        Some(_) => return Err("presets is not an array".to_owned()),
+        None => vec![],
+    };
+    let mut presets: Vec<Option<Preset<P>>> = vec![];
+    let mut unslotted = vec![];
+    for mut entry in entries {
+        let mut params = defaults.clone();
+        let mut slot = None;
+        if let Some(table) = entry.as_table_mut() {
+            if let Some(overrides) = table.remove("params") {
+                params::merge(&mut params, overrides);
+            }
+            table.insert("params".to_owned(), params);
+            slot = match table.remove("slot") {
+                Some(toml::Value::Integer(slot)) if (1..=9).contains(&slot) => {
+                    Some(slot as usize - 1)
+                }
+                Some(slot) => return Err(format!("slot {} is not 1 to 9", slot)),
+                None => None,
+            };
+        }
+        let preset: Preset<P> = entry.try_into().map_err(|error| error.to_string())?;
+        let preset = Preset {
+            params: preset.params.validate()?,
+            ..preset
+        };
+        match slot {
+            Some(slot) => {
+                if presets.len() <= slot {
+                    presets.resize_with(slot + 1, || None);
+                }
+                if presets[slot].is_some() {
+                    return Err(format!("two presets in slot {}", slot + 1));
+                }
+                presets[slot] = Some(preset);
+            }
+            None => unslotted.push(preset),
+        }
+    }
+    for preset in unslotted {
+        match presets.iter().position(Option::is_none) {
+            Some(slot) => presets[slot] = Some(preset),
+            None => presets.push(Some(preset)),
+        }
+    }
+    Ok(presets)
+}
+
+fn digit(key: Key) -> Option<usize> {
+    match key {
+        Key::Key1 | Key::Numpad1 => Some(1),
+        Key::Key2 | Key::Numpad2 => Some(2),
+        Key::Key3 | Key::Numpad3 => Some(3),
+        Key::Key4 | Key::Numpad4 => Some(4),
+        Key::Key5 | Key::Numpad5 => Some(5),
+        Key::Key6 | Key::Numpad6 => Some(6),
+        Key::Key7 | Key::Numpad7 => Some(7),
+        Key::Key8 | Key::Numpad8 => Some(8),
+        Key::Key9 | Key::Numpad9 => Some(9),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Params {
+        speed: f32,
+        count: usize,
+    }
+
+    impl Validate for Params {
+        fn validate(self) -> Result<Self, String> {
+            Ok(self)
+        }
+    }
+
+    impl Lerp for Params {
+        fn lerp(&self, to: &Self, t: f32) -> Self {
+            Params {
+                speed: self.speed.lerp(&to.speed, t),
+                count: self.count.lerp(&to.count, t),
+            }
+        }
+    }
+
+    const DEFAULTS: Params = Params {
+        speed: 1.,
+        count: 10,
+    };
+
+    fn presets() -> Presets<Params> {
+        Presets::load("/nonexistent/presets.toml", &DEFAULTS)
+    }
+
+    #[test]
+    fn read_fills_slots_and_defaults() {
+        let text = "[[presets]]\nname = 'free'\n[presets.params]\nspeed = 2.0\n\n\
+                    [[presets]]\nname = 'first'\nslot = 1\n";
+        let presets = read(text, &DEFAULTS).unwrap();
+        let names = presets
+            .iter()
+            .map(|preset| preset.as_ref().map(|preset| preset.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("first"), Some("free")]);
+        assert_eq!(presets[1].as_ref().unwrap().params.speed, 2.);
+        assert_eq!(presets[1].as_ref().unwrap().params.count, 10);
+    }
+
+    #[test]
+    fn read_rejects_bad_slots() {
+        let text = "[[presets]]\nname = 'a'\nslot = 10\n";
+        assert!(read(text, &DEFAULTS).is_err());
+        let text = "[[presets]]\nname = 'a'\nslot = 2\n[[presets]]\nname = 'b'\nslot = 2\n";
+        assert!(read(text, &DEFAULTS).is_err());
+    }
+
+    #[test]
+    fn store_keeps_other_slots() {
+        let mut presets = presets();
+        presets.store(2, "calm", &DEFAULTS);
+        assert!(presets.presets[0].is_none());
+        assert_eq!(presets.presets[2].as_ref().unwrap().name, "calm");
+        assert_eq!(presets.switch(2, &DEFAULTS), Some("calm"));
+        assert_eq!(presets.status().as_deref(), Some("preset calm"));
+    }
+
+    #[test]
+    fn characters_only_go_to_a_name_being_typed() {
+        let mut presets = presets();
+        assert!(!presets.received_character('a'));
+        presets.naming = Some(Naming {
+            index: 0,
+            name: "preset 1".to_owned(),
+            params: DEFAULTS,
+        });
+        for character in ['\u{8}', '\u{8}', 'x', '\r'] {
+            assert!(presets.received_character(character));
+        }
+        assert_eq!(presets.naming.as_ref().unwrap().name, "presetx");
+        assert!(presets.status().unwrap().contains("presetx"));
+    }
+
+    #[test]
+    fn crossfades_ease_to_the_preset() {
+        let mut presets = presets();
+        let to = Params {
+            speed: 3.,
+            count: 20,
+        };
+        presets.store(0, "fast", &to);
+        presets.switch(0, &DEFAULTS);
+        let halfway = presets.update(Duration::from_secs_f32(1.5)).unwrap();
+        assert_eq!(halfway.speed, 2.);
+        assert_eq!(presets.update(Duration::from_secs(2)), Some(to));
+        assert_eq!(presets.update(Duration::from_secs(1)), None);
+        assert_eq!(presets.status(), None);
+    }
+}
@@ -0,0 +1,46 @@
+use nannou::App;
+use std::env;
+use std::sync::OnceLock;
+
+/// A sketch compiled into the `sketchbook` binary. `build.rs` registers
+/// every sketch in `src/bin` as a module with one of these.
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub name: &'static str,
+    pub main: fn(),
+    /// Runs through `runner::run`, so `--render`, `--video` and `--poster`
+    /// work instead of a window opening.
+    pub renders: bool,
+}
+
+static CURRENT: OnceLock<(&'static str, Vec<String>)> = OnceLock::new();
+
+/// Runs `sketch` with `args` as if it was its own binary started with them.
+pub fn run(sketch: &Entry, args: Vec<String>) {
+    CURRENT
+        .set((sketch.name, args))
+        .expect("only one sketch runs at a time");
+    (sketch.main)();
+}
+
+/// The sketch running in the sketchbook, `None` in a binary of its own.
+pub fn current() -> Option<&'static str> {
+    CURRENT.get().map(|(name, _)| *name)
+}
+
+/// The running sketch's name, which captures are named after.
+pub fn name(app: &App) -> String {
+    match current() {
+        Some(name) => name.to_owned(),
+        None => app.exe_name().unwrap(),
+    }
+}
+
+/// The command line arguments meant for the sketch, without the binary and
+/// the sketch's name.
+pub fn args() -> Vec<String> {
+    match CURRENT.get() {
+        Some((_, args)) => args.clone(),
+        None => env::args().skip(1).collect(),
+    }
+}
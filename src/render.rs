@@ -0,0 +1,507 @@
+use nannou::color::{IntoLinSrgba, LinSrgba, Srgba};
+use nannou::geom::Rect;
+use nannou::image::{ImageResult, Rgba, RgbaImage};
+use nannou::lyon::math::point;
+use nannou::lyon::path::Path as LyonPath;
+use nannou::prelude::*;
+use std::path::Path;
+
+/// What a sketch's view draws on. Coordinates are the sketch's, with the
+/// origin in the center and y pointing up, like nannou's `Draw`. Lines always
+/// have round caps.
+pub trait Canvas {
+    /// The visible area, the window rect for nannou.
+    fn bounds(&self) -> Rect;
+
+    fn background(&mut self, color: impl IntoLinSrgba<f32>);
+
+    fn rect(&mut self, rect: Rect, color: impl IntoLinSrgba<f32>);
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: impl IntoLinSrgba<f32>);
+
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: impl IntoLinSrgba<f32>);
+
+    fn ellipse_outline(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    );
+
+    /// Fills the closed polygon through `points`.
+    fn polygon(&mut self, points: &[Vec2], color: impl IntoLinSrgba<f32>);
+
+    /// Fills what is inside an odd number of the closed `rings`, so rings
+    /// inside others cut holes.
+    fn region(&mut self, rings: &[Vec<Vec2>], color: impl IntoLinSrgba<f32>);
+
+    /// Fills the triangle between `corners`, blending the corners' `colors`
+    /// across it.
+    fn triangle(&mut self, corners: [Vec2; 3], colors: [LinSrgba; 3]);
+}
+
+/// Draws through nannou onto the window.
+pub struct DrawCanvas {
+    pub draw: Draw,
+    pub rect: Rect,
+}
+
+impl DrawCanvas {
+    pub fn new(app: &App) -> Self {
+        DrawCanvas {
+            draw: app.draw(),
+            rect: app.window_rect(),
+        }
+    }
+}
+
+impl Canvas for DrawCanvas {
+    fn bounds(&self) -> Rect {
+        self.rect
+    }
+
+    fn background(&mut self, color: impl IntoLinSrgba<f32>) {
+        self.draw.background().color(color.into_lin_srgba());
+    }
+
+    fn rect(&mut self, rect: Rect, color: impl IntoLinSrgba<f32>) {
+        self.draw
+            .rect()
+            .xy(rect.xy())
+            .wh(rect.wh())
+            .color(color.into_lin_srgba());
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        self.draw
+            .line()
+            .color(color.into_lin_srgba())
+            .weight(weight)
+            .caps_round()
+            .points(start, end);
+    }
+
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: impl IntoLinSrgba<f32>) {
+        self.draw
+            .ellipse()
+            .xy(center)
+            .radius(radius)
+            .color(color.into_lin_srgba());
+    }
+
+    fn ellipse_outline(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        self.draw
+            .ellipse()
+            .xy(center)
+            .radius(radius)
+            .no_fill()
+            .stroke(color.into_lin_srgba())
+            .stroke_weight(weight);
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: impl IntoLinSrgba<f32>) {
+        if points.len() < 3 {
+            return;
+        }
+        self.draw
+            .polygon()
+            .color(color.into_lin_srgba())
+            .points(points.iter().copied());
+    }
+
+    fn region(&mut self, rings: &[Vec<Vec2>], color: impl IntoLinSrgba<f32>) {
+        let mut builder = LyonPath::builder();
+        for ring in rings.iter().filter(|ring| ring.len() >= 3) {
+            builder.begin(point(ring[0].x, ring[0].y));
+            for corner in &ring[1..] {
+                builder.line_to(point(corner.x, corner.y));
+            }
+            builder.end(true);
+        }
+        // nannou only implements the even-odd fill rule.
+        self.draw
+            .path()
+            .fill()
+            .color(color.into_lin_srgba())
+            .events(builder.build().iter());
+    }
+
+    fn triangle(&mut self, corners: [Vec2; 3], colors: [LinSrgba; 3]) {
+        self.draw
+            .polygon()
+            .points_colored(corners.into_iter().zip(colors));
+    }
+}
+
+/// CPU rasterizer. Pixels are kept as linear floats and blended like nannou
+/// does on the GPU, shapes are anti-aliased from their signed distance.
+#[derive(Clone, Debug)]
+pub struct Raster {
+    width: u32,
+    height: u32,
+    view: Rect,
+    bounds: Rect,
+    pixels: Vec<LinSrgba>,
+}
+
+impl Raster {
+    /// Opaque black `width` x `height` pixels showing `view` of the sketch.
+    pub fn new(width: u32, height: u32, view: Rect) -> Self {
+        Raster {
+            width,
+            height,
+            view,
+            bounds: view,
+            pixels: vec![LinSrgba::new(0., 0., 0., 1.); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn view(&self) -> Rect {
+        self.view
+    }
+
+    /// Moves the camera, the pixels stay as they are.
+    pub fn set_view(&mut self, view: Rect) {
+        self.view = view;
+    }
+
+    /// What `Canvas::bounds` reports, the view unless the raster is one tile
+    /// of a larger picture.
+    pub fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+    }
+
+    /// Pixels per sketch unit.
+    pub fn scale(&self) -> f32 {
+        self.width as f32 / self.view.w()
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> LinSrgba {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// All pixels row by row from the top left.
+    pub fn pixels(&self) -> &[LinSrgba] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [LinSrgba] {
+        &mut self.pixels
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let color = Srgba::from_linear(self.pixel(x, y));
+            Rgba([
+                to_u8(color.red),
+                to_u8(color.green),
+                to_u8(color.blue),
+                to_u8(color.alpha),
+            ])
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> ImageResult<()> {
+        self.to_image().save(path)
+    }
+
+    fn to_pixel(&self, point: Vec2) -> Vec2 {
+        vec2(
+            (point.x - self.view.left()) * self.scale(),
+            (self.view.top() - point.y) * self.scale(),
+        )
+    }
+
+    /// Fills inside an odd number of `rings`, anti-aliased by the distance to
+    /// the nearest edge. Row by row, the edges are the ones near the row and
+    /// the inside is between every other crossing, so distances are only
+    /// measured for pixels less than one away from an edge.
+    fn fill_rings(&mut self, rings: &[&[Vec2]], color: LinSrgba) {
+        let mut edges = vec![];
+        for ring in rings.iter().filter(|ring| ring.len() >= 3) {
+            let ring = ring
+                .iter()
+                .map(|&point| self.to_pixel(point))
+                .collect::<Vec<_>>();
+            edges.extend(
+                ring.iter()
+                    .copied()
+                    .zip(ring.iter().copied().cycle().skip(1)),
+            );
+        }
+        if edges.is_empty() {
+            return;
+        }
+        edges.sort_by(|(a, b), (c, d)| a.y.min(b.y).total_cmp(&c.y.min(d.y)));
+        let points = edges.iter().map(|(a, _)| *a);
+        let min = points
+            .clone()
+            .fold(Vec2::splat(f32::MAX), |min, p| min.min(p));
+        let max = points.fold(Vec2::splat(f32::MIN), |max, p| max.max(p));
+        let x_min = (min.x - 1.).floor().max(0.) as u32;
+        let x_max = ((max.x + 1.).ceil().max(0.) as u32).min(self.width);
+        let y_min = (min.y - 1.).floor().max(0.) as u32;
+        let y_max = ((max.y + 1.).ceil().max(0.) as u32).min(self.height);
+
+        let mut next = 0;
+        let mut active: Vec<(Vec2, Vec2)> = vec![];
+        let mut crossings = vec![];
+        let mut near = vec![false; x_max.saturating_sub(x_min) as usize];
+        for y in y_min..y_max {
+            let center = y as f32 + 0.5;
+            // Edges reaching within a pixel of the row's centers.
+            while next < edges.len() && edges[next].0.y.min(edges[next].1.y) <= center + 1. {
+                active.push(edges[next]);
+                next += 1;
+            }
+            active.retain(|(a, b)| a.y.max(b.y) >= center - 1.);
+
+            // Crossings as in `contains`, so both agree on what is inside.
+            crossings.clear();
+            crossings.extend(
+                active
+                    .iter()
+                    .filter(|(a, b)| (a.y > center) != (b.y > center))
+                    .map(|(a, b)| a.x + (center - a.y) / (b.y - a.y) * (b.x - a.x)),
+            );
+            crossings.sort_by(f32::total_cmp);
+
+            near.fill(false);
+            for (a, b) in active.iter() {
+                let (low, high) = if a.y == b.y {
+                    (a.x.min(b.x), a.x.max(b.x))
+                } else {
+                    let t = |y: f32| ((y - a.y) / (b.y - a.y)).clamp(0., 1.);
+                    let x0 = a.x + (b.x - a.x) * t(center - 1.);
+                    let x1 = a.x + (b.x - a.x) * t(center + 1.);
+                    (x0.min(x1), x0.max(x1))
+                };
+                let start = ((low - 1.).floor().max(x_min as f32) as u32).min(x_max);
+                let end = ((high + 1.).ceil().max(x_min as f32) as u32).min(x_max);
+                near[(start - x_min) as usize..(end - x_min) as usize].fill(true);
+            }
+
+            let mut passed = 0;
+            for x in x_min..x_max {
+                let p = vec2(x as f32 + 0.5, center);
+                while passed < crossings.len() && crossings[passed] <= p.x {
+                    passed += 1;
+                }
+                let inside = passed % 2 == 1;
+                let coverage = if near[(x - x_min) as usize] {
+                    let distance = active
+                        .iter()
+                        .map(|&(a, b)| distance_to_segment(p, a, b))
+                        .fold(f32::MAX, f32::min);
+                    if inside {
+                        distance + 0.5
+                    } else {
+                        0.5 - distance
+                    }
+                } else if inside {
+                    1.
+                } else {
+                    continue;
+                };
+                self.blend(x, y, color, coverage);
+            }
+        }
+    }
+
+    /// Blends `color` into every pixel of the pixel space box `min`..`max`,
+    /// weighted by the coverage of the pixel center.
+    fn fill(&mut self, min: Vec2, max: Vec2, color: LinSrgba, coverage: impl Fn(Vec2) -> f32) {
+        let x_min = min.x.floor().max(0.) as u32;
+        let y_min = min.y.floor().max(0.) as u32;
+        let x_max = (max.x.ceil().max(0.) as u32).min(self.width);
+        let y_max = (max.y.ceil().max(0.) as u32).min(self.height);
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                self.blend(x, y, color, coverage(vec2(x as f32 + 0.5, y as f32 + 0.5)));
+            }
+        }
+    }
+
+    /// Blends `color` into pixel `x`, `y` by `coverage`, 0 to 1.
+    fn blend(&mut self, x: u32, y: u32, color: LinSrgba, coverage: f32) {
+        let alpha = coverage.clamp(0., 1.) * color.alpha;
+        if alpha <= 0. {
+            return;
+        }
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        pixel.red = color.red * alpha + pixel.red * (1. - alpha);
+        pixel.green = color.green * alpha + pixel.green * (1. - alpha);
+        pixel.blue = color.blue * alpha + pixel.blue * (1. - alpha);
+        pixel.alpha = alpha + pixel.alpha * (1. - alpha);
+    }
+}
+
+impl Canvas for Raster {
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn background(&mut self, color: impl IntoLinSrgba<f32>) {
+        let color = color.into_lin_srgba();
+        self.pixels.iter_mut().for_each(|pixel| *pixel = color);
+    }
+
+    fn rect(&mut self, rect: Rect, color: impl IntoLinSrgba<f32>) {
+        let a = self.to_pixel(rect.top_left());
+        let b = self.to_pixel(rect.bottom_right());
+        self.fill(a - 0.5, b + 0.5, color.into_lin_srgba(), |p| {
+            let inside = (p - a).min(b - p).min_element();
+            inside + 0.5
+        });
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        let a = self.to_pixel(start);
+        let b = self.to_pixel(end);
+        let radius = weight * self.scale() / 2.;
+        let min = a.min(b) - radius - 1.;
+        let max = a.max(b) + radius + 1.;
+        self.fill(min, max, color.into_lin_srgba(), |p| {
+            stroke_coverage(distance_to_segment(p, a, b), radius)
+        });
+    }
+
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: impl IntoLinSrgba<f32>) {
+        let c = self.to_pixel(center);
+        let radius = radius.abs() * self.scale();
+        self.fill(
+            c - radius - 1.,
+            c + radius + 1.,
+            color.into_lin_srgba(),
+            |p| radius + 0.5 - p.distance(c),
+        );
+    }
+
+    fn ellipse_outline(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let c = self.to_pixel(center);
+        let radius = radius.abs() * self.scale();
+        let half = weight * self.scale() / 2.;
+        let outer = radius + half;
+        self.fill(
+            c - outer - 1.,
+            c + outer + 1.,
+            color.into_lin_srgba(),
+            |p| stroke_coverage((p.distance(c) - radius).abs(), half),
+        );
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: impl IntoLinSrgba<f32>) {
+        self.fill_rings(&[points], color.into_lin_srgba());
+    }
+
+    fn region(&mut self, rings: &[Vec<Vec2>], color: impl IntoLinSrgba<f32>) {
+        let rings = rings.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        self.fill_rings(&rings, color.into_lin_srgba());
+    }
+
+    /// Not anti-aliased. Pixels on a side belong to one of the two
+    /// triangles sharing it, so a triangulation fills without seams or
+    /// overlaps.
+    fn triangle(&mut self, corners: [Vec2; 3], mut colors: [LinSrgba; 3]) {
+        let [a, mut b, mut c] = corners.map(|corner| self.to_pixel(corner));
+        let mut area = (b - a).perp_dot(c - a);
+        if area == 0. || !area.is_finite() {
+            return;
+        }
+        if area < 0. {
+            (b, c) = (c, b);
+            colors.swap(1, 2);
+            area = -area;
+        }
+        // Neighbours run along a shared side in opposite directions, so
+        // exactly one of them owns the pixels centered on it.
+        let sides = [(b, c), (c, a), (a, b)];
+        let owned = sides.map(|(start, end)| {
+            let side = end - start;
+            side.y > 0. || (side.y == 0. && side.x < 0.)
+        });
+        let min = a.min(b).min(c).floor().max(Vec2::ZERO);
+        let max = (a.max(b).max(c).ceil() + 1.).min(vec2(self.width as f32, self.height as f32));
+        for y in min.y as u32..max.y as u32 {
+            for x in min.x as u32..max.x as u32 {
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                // Barycentric weights, all positive inside.
+                let weights = sides.map(|(start, end)| (end - start).perp_dot(p - start) / area);
+                let inside = weights
+                    .iter()
+                    .zip(owned)
+                    .all(|(&weight, owned)| weight > 0. || (weight == 0. && owned));
+                if !inside {
+                    continue;
+                }
+                let mut color = LinSrgba::new(0., 0., 0., 0.);
+                for (weight, corner) in weights.iter().zip(colors) {
+                    color.red += corner.red * weight;
+                    color.green += corner.green * weight;
+                    color.blue += corner.blue * weight;
+                    color.alpha += corner.alpha * weight;
+                }
+                self.blend(x, y, color, 1.);
+            }
+        }
+    }
+}
+
+pub fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0. {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(ab) / length_squared).clamp(0., 1.);
+    p.distance(a + ab * t)
+}
+
+/// Whether `point` is inside the closed polygon through `polygon`, by the
+/// even-odd rule.
+pub fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Coverage of a pixel `distance` away from the center of a stroke. Strokes
+/// thinner than a pixel fade out instead of breaking up.
+fn stroke_coverage(distance: f32, half_width: f32) -> f32 {
+    if half_width >= 0.5 {
+        half_width + 0.5 - distance
+    } else {
+        2. * half_width * (1. - distance)
+    }
+}
+
+pub(crate) fn to_u8(channel: f32) -> u8 {
+    (channel.clamp(0., 1.) * 255.).round() as u8
+}
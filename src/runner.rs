@@ -0,0 +1,322 @@
+use crate::args::Args;
+use crate::clock::Clock;
+use crate::headless::Headless;
+use crate::input::{self, Controls};
+use crate::poster::Poster;
+use crate::registry;
+use crate::render::{Canvas, DrawCanvas, Raster};
+use crate::sampling::seed_from_args;
+use crate::trails::{Fade, Trails};
+use crate::video::Video;
+use nannou::prelude::*;
+use std::num::NonZeroU32;
+use std::process;
+use std::time::Duration;
+
+/// A sketch run by `run`, which owns the window, clock and standard controls
+/// and calls these hooks.
+pub trait Sketch: Clone + 'static {
+    /// Builds the sketch, seeded by `context.controls.seed`.
+    fn init(context: &mut Context) -> Self;
+
+    /// Advances the simulation one step of `context.clock.step()` seconds.
+    fn update(&mut self, context: &Context);
+
+    /// Draws the current state, on the window or any other canvas.
+    fn draw(&self, context: &Context, canvas: &mut impl Canvas);
+
+    /// Sees every frame and window event before the runner does. Returning
+    /// true keeps the runner from handling it, so a sketch can rebind the
+    /// standard keys.
+    fn event(&mut self, _app: &App, _context: &mut Context, _event: &Event) -> bool {
+        false
+    }
+
+    /// Draws into fading trails instead of straight onto the output, `None`
+    /// by default.
+    fn trails(&self) -> Option<Fade> {
+        None
+    }
+
+    fn window() -> Window {
+        Window::default()
+    }
+}
+
+/// How the window is opened.
+#[derive(Clone, Debug)]
+pub struct Window {
+    pub fullscreen: bool,
+    /// Size when not fullscreen.
+    pub size: (u32, u32),
+    /// The sketch's name by default.
+    pub title: Option<String>,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window {
+            fullscreen: true,
+            size: (1920, 1080),
+            title: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// Once per frame before the simulation steps, with the real time since
+    /// the last one.
+    Frame(Duration),
+    Window(WindowEvent),
+}
+
+/// What the runner keeps for a sketch and shares with its hooks.
+#[derive(Clone, Debug)]
+pub struct Context {
+    pub controls: Controls,
+    pub clock: Clock,
+}
+
+impl Context {
+    pub fn new(seed: u64) -> Self {
+        Context {
+            controls: Controls::new(seed),
+            clock: Clock::default(),
+        }
+    }
+}
+
+/// A sketch with its context, advanced and drawn the same way on the window
+/// and off it.
+#[derive(Clone, Debug)]
+pub struct Runner<S> {
+    pub context: Context,
+    pub sketch: S,
+    trails: Option<Trails>,
+    /// The trails resolved for the window, rewritten in place every frame.
+    texture: Option<wgpu::Texture>,
+    bytes: Vec<u8>,
+}
+
+impl<S: Sketch> Runner<S> {
+    pub fn new(context: Context, sketch: S) -> Self {
+        Runner {
+            context,
+            sketch,
+            trails: None,
+            texture: None,
+            bytes: vec![],
+        }
+    }
+
+    /// The sketch `steps` simulation steps after starting with `seed`.
+    pub fn start(seed: u64, steps: u64) -> Self {
+        let mut context = Context::new(seed);
+        let sketch = S::init(&mut context);
+        let mut runner = Runner::new(context, sketch);
+        for _ in 0..steps {
+            runner.step();
+        }
+        runner
+    }
+
+    /// Started from `--seed` and `--steps`.
+    pub fn from_args() -> Self {
+        Self::start(seed_from_args(), Args::parse().steps.unwrap_or(0))
+    }
+
+    pub fn step(&mut self) {
+        self.context.clock.tick();
+        self.sketch.update(&self.context);
+    }
+
+    /// Runs as many steps as the clock gives for `elapsed` real time.
+    pub fn advance(&mut self, elapsed: Duration) {
+        for _ in 0..self.context.clock.advance(elapsed) {
+            self.step();
+        }
+    }
+
+    /// Like `advance` but with `Clock::advance_exact`, for runs off the
+    /// window.
+    pub fn advance_exact(&mut self, elapsed: Duration) {
+        for _ in 0..self.context.clock.advance_exact(elapsed) {
+            self.step();
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut impl Canvas) {
+        self.sketch.draw(&self.context, canvas);
+    }
+
+    /// Draws onto `raster`, through the trails if the sketch has them.
+    pub fn render(&mut self, raster: &mut Raster) {
+        let Some(fade) = self.sketch.trails() else {
+            self.trails = None;
+            self.draw(raster);
+            return;
+        };
+        let trails = match &mut self.trails {
+            Some(trails) if trails.matches(raster) => trails,
+            trails => trails.insert(Trails::matching(fade, raster)),
+        };
+        draw_trails(&self.sketch, &self.context, trails, fade);
+        trails.resolve(raster);
+    }
+
+    /// Draws into trails of the window's size, cleared first while `clear`,
+    /// and uploads them to the window's texture, if the sketch has trails.
+    fn render_window(&mut self, app: &App, clear: bool) {
+        let Some(fade) = self.sketch.trails() else {
+            self.trails = None;
+            self.texture = None;
+            return;
+        };
+        let (width, height) = app.main_window().inner_size_pixels();
+        let view = app.window_rect();
+        let trails = match &mut self.trails {
+            Some(trails) if trails.fits(width, height, view) => trails,
+            trails => trails.insert(Trails::new(fade, width, height, view)),
+        };
+        if clear {
+            trails.clear();
+        }
+        draw_trails(&self.sketch, &self.context, trails, fade);
+        trails.resolve_bytes(&mut self.bytes);
+
+        let window = app.main_window();
+        let texture = match &self.texture {
+            Some(texture) if texture.size() == [width, height] => texture,
+            _ => self.texture.insert(
+                wgpu::TextureBuilder::new()
+                    .size([width, height])
+                    .format(wgpu::TextureFormat::Rgba8UnormSrgb)
+                    .usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING)
+                    .build(window.device()),
+            ),
+        };
+        window.queue().write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * width),
+                rows_per_image: None,
+            },
+            texture.extent(),
+        );
+    }
+}
+
+/// Fades `trails` to the current time and draws the sketch into them.
+fn draw_trails<S: Sketch>(sketch: &S, context: &Context, trails: &mut Trails, fade: Fade) {
+    trails.fade = fade;
+    trails.advance(context.clock.time(), context.clock.step());
+    sketch.draw(context, trails);
+}
+
+/// Runs `S` headless, as a video or a poster when the flags ask for it, in a
+/// window otherwise. The window has the standard controls of
+/// `input::key_released`, Delete clears it or the trails while held and the
+/// info overlay and parameter panel are drawn on top.
+pub fn run<S: Sketch>() {
+    let args = Args::parse();
+    let frame = Duration::from_secs_f32(1. / 60.);
+    if let Some(video) = Video::from_args(&args) {
+        let mut runner = Runner::<S>::from_args();
+        video.run(|raster, elapsed| {
+            runner.advance_exact(elapsed);
+            runner.render(raster);
+        });
+        return;
+    }
+    if let Some(poster) = Poster::from_args(&args) {
+        poster.run(&Runner::<S>::from_args(), |runner, raster| {
+            runner.advance_exact(frame);
+            runner.render(raster);
+        });
+        return;
+    }
+    if let Some(headless) = Headless::from_args(&args) {
+        let mut runner = Runner::<S>::from_args();
+        let saved = headless.run(|raster| {
+            runner.advance_exact(frame);
+            runner.render(raster);
+        });
+        if let Err(error) = saved {
+            eprintln!("failed to save {}: {}", headless.path.display(), error);
+            process::exit(1);
+        }
+        return;
+    }
+    nannou::app(model::<S>).update(update::<S>).run();
+}
+
+fn model<S: Sketch>(app: &App) -> Runner<S> {
+    let options = S::window();
+    let title = options.title.unwrap_or_else(|| registry::name(app));
+    let mut window = app
+        .new_window()
+        .title(title)
+        .size(options.size.0, options.size.1)
+        .view(view::<S>)
+        .event(event::<S>);
+    if options.fullscreen {
+        window = window.fullscreen();
+    }
+    window.build().unwrap();
+
+    Runner::from_args()
+}
+
+fn update<S: Sketch>(app: &App, runner: &mut Runner<S>, update: Update) {
+    let event = Event::Frame(update.since_last);
+    if !runner.sketch.event(app, &mut runner.context, &event) {
+        runner.advance(update.since_last);
+    }
+    input::capture_requested(app, &mut runner.context.controls, &runner.context.clock);
+    if runner.context.clock.frozen() {
+        return;
+    }
+    runner.render_window(app, input::clear_requested(app));
+}
+
+fn event<S: Sketch>(app: &App, runner: &mut Runner<S>, event: WindowEvent) {
+    let event = Event::Window(event);
+    if runner.sketch.event(app, &mut runner.context, &event) {
+        return;
+    }
+    if let Event::Window(KeyReleased(key)) = event {
+        let context = &mut runner.context;
+        input::key_released(&mut context.controls, &mut context.clock, key);
+    }
+}
+
+fn view<S: Sketch>(app: &App, runner: &Runner<S>, frame: Frame) {
+    let context = &runner.context;
+    if context.clock.frozen() {
+        return;
+    }
+
+    let mut canvas = DrawCanvas::new(app);
+    if let Some(texture) = runner.trails.as_ref().and(runner.texture.as_ref()) {
+        canvas.draw.texture(texture).wh(app.window_rect().wh());
+    } else {
+        if input::clear_requested(app) {
+            canvas.background(BLACK);
+        }
+        runner.draw(&mut canvas);
+    }
+
+    input::draw_info(app, &canvas.draw, &context.controls, &context.clock);
+    input::draw_status(app, &canvas.draw, &context.controls);
+    context.controls.panel.draw(&canvas.draw);
+
+    canvas.draw.to_frame(app, &frame).unwrap();
+}
@@ -0,0 +1,52 @@
+use crate::args::Args;
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{random, Rng, SeedableRng};
+use std::f32::consts::PI;
+
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// The `--seed` flag, or a fresh random seed. Printed so a run can be
+/// replayed later.
+pub fn seed_from_args() -> u64 {
+    let seed = Args::parse().seed.unwrap_or_else(random);
+    eprintln!("seed {}", seed);
+    seed
+}
+
+/// Uniformly distributed point inside the disc of radius `r` around `o`.
+pub fn random_point_in_radius(rng: &mut impl Rng, o: &Vec2, r: f32) -> Vec2 {
+    let r = r * rng.gen::<f32>().sqrt();
+    let t = rng.gen::<f32>() * 2.0 * PI;
+    vec2(o.x + r * t.cos(), o.y + r * t.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_is_deterministic() {
+        let draw = |seed| seeded(seed).gen::<u64>();
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+
+    #[test]
+    fn points_fill_the_disc_uniformly() {
+        let mut rng = seeded(1);
+        let center = vec2(100., -50.);
+        let points = (0..10_000)
+            .map(|_| random_point_in_radius(&mut rng, &center, 10.))
+            .collect::<Vec<Vec2>>();
+        assert!(points.iter().all(|point| point.distance(center) <= 10.));
+        // Uniform over the area puts a quarter inside half the radius.
+        let inner = points
+            .iter()
+            .filter(|point| point.distance(center) <= 5.)
+            .count();
+        assert!((2300..2700).contains(&inner), "{}", inner);
+    }
+}
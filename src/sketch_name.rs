@@ -0,0 +1,12 @@
+// Included by `build.rs` and `sketches.rs` instead of being a module, so the
+// build script sees the same sketch names as the library.
+
+/// Sketch names look like `2023-12-03-14-31-35`, the time they were made.
+pub fn is_sketch(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 19
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            4 | 7 | 10 | 13 | 16 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `src/bin` of the crate, where every sketch is a timestamped binary.
+pub fn dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/bin")
+}
+
+pub fn source(name: &str) -> PathBuf {
+    dir().join(name).with_extension("rs")
+}
+
+include!("sketch_name.rs");
+
+/// When a sketch was made, in seconds since the epoch.
+pub fn timestamp(name: &str) -> Option<i64> {
+    if !is_sketch(name) {
+        return None;
+    }
+    let part = |range: std::ops::Range<usize>| name[range].parse::<i64>().unwrap();
+    let (year, month, day) = (part(0..4), part(5..7), part(8..10));
+    // Days from the civil calendar, shifted so years start in March.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + part(11..13) * 3600 + part(14..16) * 60 + part(17..19))
+}
+
+/// All sketches, oldest first.
+pub fn list() -> Vec<String> {
+    let mut sketches = fs::read_dir(dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    let name = name.strip_suffix(".rs")?;
+                    is_sketch(name).then(|| name.to_owned())
+                })
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    sketches.sort();
+    sketches
+}
+
+/// Picks a sketch like `luna` does. Nothing is the newest, `-n` the nth
+/// newest, `n` the nth oldest counting from 1, anything else a name or a
+/// fuzzy match on it, newest first among equally good matches.
+pub fn find<'a>(sketches: &'a [String], selector: Option<&str>) -> Result<&'a str, String> {
+    let selector = match selector {
+        None => {
+            return sketches
+                .last()
+                .map(|s| s.as_str())
+                .ok_or("no sketches".to_owned())
+        }
+        Some(selector) => selector.trim_end_matches(".rs"),
+    };
+    let selector = selector.rsplit('/').next().unwrap_or(selector);
+    if let Some(back) = selector
+        .strip_prefix('-')
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        return back
+            .checked_sub(1)
+            .and_then(|back| sketches.len().checked_sub(back + 1))
+            .map(|i| sketches[i].as_str())
+            .ok_or_else(|| format!("there are only {} sketches", sketches.len()));
+    }
+    if let Ok(index) = selector.parse::<usize>() {
+        if index >= 1 && index <= sketches.len() && selector.len() < 4 {
+            return Ok(&sketches[index - 1]);
+        }
+    }
+    if let Some(exact) = sketches.iter().find(|sketch| *sketch == selector) {
+        return Ok(exact);
+    }
+    sketches
+        .iter()
+        .enumerate()
+        .filter_map(|(i, sketch)| fuzzy_score(selector, sketch).map(|score| (score, i)))
+        .max()
+        .map(|(_, i)| sketches[i].as_str())
+        .ok_or_else(|| format!("no sketch matches {}", selector))
+}
+
+/// How well `query` matches `name` as a subsequence, ignoring dashes.
+/// Consecutive runs score higher, so `1203` prefers `..-12-03-..` over
+/// scattered digits.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let query = query.chars().filter(|c| *c != '-').collect::<Vec<char>>();
+    let name = name.chars().filter(|c| *c != '-').collect::<Vec<char>>();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let mut score = 0;
+    let mut run = 0;
+    let mut q = 0;
+    for c in name.iter() {
+        if q < query.len() && *c == query[q] {
+            q += 1;
+            run += 1;
+            score += run;
+        } else {
+            run = 0;
+        }
+    }
+    (q == query.len()).then_some(score)
+}
+
+/// Where each sketch was copied from, kept in `lineage.toml` at the root of
+/// the crate.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub parents: BTreeMap<String, String>,
+}
+
+impl Lineage {
+    pub fn path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("lineage.toml")
+    }
+
+    /// The recorded lineage, empty without a `lineage.toml`. A file that
+    /// can't be read or parsed is an error, so nothing saves over it.
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path();
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|error| format!("{}: {}", path.display(), error))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Lineage::default()),
+            Err(error) => Err(format!("{}: {}", path.display(), error)),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let text = toml::to_string_pretty(self).expect("lineage is not toml");
+        fs::write(Self::path(), text)
+    }
+
+    pub fn parent(&self, sketch: &str) -> Option<&str> {
+        self.parents.get(sketch).map(|parent| parent.as_str())
+    }
+
+    pub fn children<'a>(&'a self, sketch: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.parents
+            .iter()
+            .filter(move |(_, parent)| *parent == sketch)
+            .map(|(child, _)| child.as_str())
+    }
+}
+
+/// The top level `const`s of a sketch's source with their values, in
+/// order, whitespace collapsed.
+pub fn constants(source: &str) -> Vec<(String, String)> {
+    let mut constants = vec![];
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let rest = match line.strip_prefix("const ") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let mut definition = rest.to_owned();
+        while !definition.trim_end().ends_with(';') {
+            match lines.next() {
+                Some(line) => {
+                    definition.push(' ');
+                    definition.push_str(line);
+                }
+                None => break,
+            }
+        }
+        let (name, value) = match definition.split_once('=') {
+            Some((declaration, value)) => (declaration, value),
+            None => continue,
+        };
+        let name = name.split(':').next().unwrap_or(name).trim().to_owned();
+        let value = value.trim().trim_end_matches(';');
+        constants.push((name, value.split_whitespace().collect::<Vec<_>>().join(" ")));
+    }
+    constants
+}
+
+/// `NAME old -> new` for every constant that differs, `+NAME value` and
+/// `-NAME value` for ones only in one of them. Numbers only differ by value,
+/// `2.0` and `2.` are the same.
+pub fn diff_constants(old: &[(String, String)], new: &[(String, String)]) -> Vec<String> {
+    let same = |a: &str, b: &str| match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    };
+    let old_map = old.iter().cloned().collect::<BTreeMap<String, String>>();
+    let new_map = new.iter().cloned().collect::<BTreeMap<String, String>>();
+    let mut changes = vec![];
+    for (name, value) in new.iter() {
+        match old_map.get(name) {
+            Some(old) if !same(old, value) => {
+                changes.push(format!("{} {} -> {}", name, old, value))
+            }
+            Some(_) => (),
+            None => changes.push(format!("+{} {}", name, value)),
+        }
+    }
+    for (name, value) in old.iter() {
+        if !new_map.contains_key(name) {
+            changes.push(format!("-{} {}", name, value));
+        }
+    }
+    changes
+}
+
+/// Every value of a TOML document under its dotted key, like
+/// `gradient.0.hue`.
+pub fn flatten_toml(value: &toml::Value) -> Vec<(String, String)> {
+    fn walk(prefix: &str, value: &toml::Value, out: &mut Vec<(String, String)>) {
+        let key = |part: &str| match prefix {
+            "" => part.to_owned(),
+            _ => format!("{}.{}", prefix, part),
+        };
+        match value {
+            toml::Value::Table(table) => {
+                for (name, value) in table {
+                    walk(&key(name), value, out);
+                }
+            }
+            toml::Value::Array(array) => {
+                for (i, value) in array.iter().enumerate() {
+                    walk(&key(&i.to_string()), value, out);
+                }
+            }
+            value => out.push((prefix.to_owned(), value.to_string())),
+        }
+    }
+    let mut out = vec![];
+    walk("", value, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sketches() -> Vec<String> {
+        [
+            "2023-11-30-09-00-00",
+            "2023-12-01-10-20-30",
+            "2023-12-03-14-31-35",
+        ]
+        .map(str::to_owned)
+        .to_vec()
+    }
+
+    #[test]
+    fn is_sketch_checks_the_shape() {
+        assert!(is_sketch("2023-12-03-14-31-35"));
+        assert!(!is_sketch("2023-12-03-14-31"));
+        assert!(!is_sketch("2023-12-03_14-31-35"));
+        assert!(!is_sketch("luna"));
+    }
+
+    #[test]
+    fn timestamp_counts_seconds_since_the_epoch() {
+        assert_eq!(timestamp("1970-01-01-00-00-00"), Some(0));
+        assert_eq!(timestamp("2000-03-01-00-00-00"), Some(951_868_800));
+        assert_eq!(timestamp("2023-12-03-14-31-35"), Some(1_701_613_895));
+        assert_eq!(timestamp("luna"), None);
+    }
+
+    #[test]
+    fn find_by_position() {
+        let sketches = sketches();
+        assert_eq!(find(&sketches, None), Ok("2023-12-03-14-31-35"));
+        assert_eq!(find(&sketches, Some("1")), Ok("2023-11-30-09-00-00"));
+        assert_eq!(find(&sketches, Some("-2")), Ok("2023-12-01-10-20-30"));
+        assert!(find(&sketches, Some("-4")).is_err());
+        assert!(find(&[], None).is_err());
+    }
+
+    #[test]
+    fn find_by_name() {
+        let sketches = sketches();
+        assert_eq!(
+            find(&sketches, Some("src/bin/2023-12-01-10-20-30.rs")),
+            Ok("2023-12-01-10-20-30")
+        );
+        assert_eq!(find(&sketches, Some("1203")), Ok("2023-12-03-14-31-35"));
+        assert_eq!(find(&sketches, Some("11-30")), Ok("2023-11-30-09-00-00"));
+        assert!(find(&sketches, Some("x")).is_err());
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_runs() {
+        assert_eq!(fuzzy_score("", "2023"), Some(0));
+        assert_eq!(fuzzy_score("24", "2023"), None);
+        let run = fuzzy_score("1203", "2023-12-03-14-31-35").unwrap();
+        let scattered = fuzzy_score("1203", "2023-11-02-10-00-03").unwrap();
+        assert!(run > scattered);
+    }
+
+    #[test]
+    fn constants_collects_top_level_consts() {
+        let source = "use x;\nconst A: f32 = 1.;\nfn f() {\n    const B: u32 = 2;\n}\nconst C: [f32; 2] = [\n    1.,\n    2.,\n];\n";
+        assert_eq!(
+            constants(source),
+            [
+                ("A".to_owned(), "1.".to_owned()),
+                ("C".to_owned(), "[ 1., 2., ]".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_constants_compares_numbers_by_value() {
+        let old = constants("const A: f32 = 2.;\nconst B: f32 = 1.;\nconst C: u8 = 1;\n");
+        let new = constants("const A: f32 = 2.0;\nconst B: f32 = 3.;\nconst D: u8 = 4;\n");
+        assert_eq!(diff_constants(&old, &new), ["B 1. -> 3.", "+D 4", "-C 1"]);
+    }
+
+    #[test]
+    fn flatten_toml_uses_dotted_keys() {
+        let value: toml::Value = "a = 1\nb = [2, 3]\n[c]\nd = 'e'\n".parse().unwrap();
+        assert_eq!(
+            flatten_toml(&value),
+            [
+                ("a".to_owned(), "1".to_owned()),
+                ("b.0".to_owned(), "2".to_owned()),
+                ("b.1".to_owned(), "3".to_owned()),
+                ("c.d".to_owned(), "\"e\"".to_owned()),
+            ]
+        );
+    }
+}
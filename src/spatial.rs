@@ -0,0 +1,310 @@
+use nannou::prelude::*;
+use ordered_float::OrderedFloat;
+
+/// Uniform grid over a set of positions, meant to be rebuilt every frame.
+/// Queries return indices into the positions the grid was built from.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    positions: Vec<Vec2>,
+    min: Vec2,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cell_starts: Vec<usize>,
+    entries: Vec<usize>,
+}
+
+impl Grid {
+    /// Picks a cell size that puts about two positions into each cell.
+    pub fn new(positions: impl IntoIterator<Item = Vec2>) -> Self {
+        let positions = positions.into_iter().collect::<Vec<Vec2>>();
+        let (min, max) = bounds(&positions);
+        let size = max - min;
+        let cell_size =
+            (size.x.max(1.) * size.y.max(1.) * 2. / positions.len().max(1) as f32).sqrt();
+        Self::build(positions, min, max, cell_size)
+    }
+
+    pub fn with_cell_size(positions: impl IntoIterator<Item = Vec2>, cell_size: f32) -> Self {
+        let positions = positions.into_iter().collect::<Vec<Vec2>>();
+        let (min, max) = bounds(&positions);
+        Self::build(positions, min, max, cell_size)
+    }
+
+    fn build(positions: Vec<Vec2>, min: Vec2, max: Vec2, cell_size: f32) -> Self {
+        let cell_size = cell_size.max(f32::EPSILON);
+        let columns = ((max.x - min.x) / cell_size) as usize + 1;
+        let rows = ((max.y - min.y) / cell_size) as usize + 1;
+
+        let mut grid = Grid {
+            positions,
+            min,
+            cell_size,
+            columns,
+            rows,
+            cell_starts: vec![0; columns * rows + 1],
+            entries: vec![],
+        };
+
+        // counting sort of the indices by cell
+        let cells = grid
+            .positions
+            .iter()
+            .map(|position| {
+                let (column, row) = grid.cell(*position);
+                row as usize * columns + column as usize
+            })
+            .collect::<Vec<usize>>();
+        for cell in cells.iter() {
+            grid.cell_starts[cell + 1] += 1;
+        }
+        for i in 1..grid.cell_starts.len() {
+            grid.cell_starts[i] += grid.cell_starts[i - 1];
+        }
+        let mut next = grid.cell_starts.clone();
+        grid.entries = vec![0; cells.len()];
+        for (index, cell) in cells.iter().enumerate() {
+            grid.entries[next[*cell]] = index;
+            next[*cell] += 1;
+        }
+
+        grid
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn position(&self, index: usize) -> Vec2 {
+        self.positions[index]
+    }
+
+    /// Every index within `radius` of `point`, in no particular order.
+    pub fn within_radius(&self, point: Vec2, radius: f32) -> Vec<usize> {
+        let (column_min, row_min) = self.cell(point - vec2(radius, radius));
+        let (column_max, row_max) = self.cell(point + vec2(radius, radius));
+        let mut found = vec![];
+        for row in row_min.max(0)..=row_max.min(self.rows as isize - 1) {
+            for column in column_min.max(0)..=column_max.min(self.columns as isize - 1) {
+                found.extend(
+                    self.cell_entries(column, row)
+                        .iter()
+                        .filter(|&&index| self.positions[index].distance(point) <= radius),
+                );
+            }
+        }
+        found
+    }
+
+    /// The `k` indices closest to `point`, closest first.
+    pub fn nearest_k(&self, point: Vec2, k: usize) -> Vec<usize> {
+        self.nearest_k_filtered(point, k, |_| true)
+    }
+
+    pub fn nearest(&self, point: Vec2) -> Option<usize> {
+        self.nearest_k(point, 1).first().copied()
+    }
+
+    /// Closest index to `point` other than `index`. Finding a particle's
+    /// neighbour is `nearest_excluding(particle.position, i)`.
+    pub fn nearest_excluding(&self, point: Vec2, index: usize) -> Option<usize> {
+        self.nearest_k_filtered(point, 1, |other| other != index)
+            .first()
+            .copied()
+    }
+
+    /// Searches rings of cells around `point` outwards until the `k`th
+    /// candidate is closer than anything a further ring could hold.
+    pub fn nearest_k_filtered(
+        &self,
+        point: Vec2,
+        k: usize,
+        filter: impl Fn(usize) -> bool,
+    ) -> Vec<usize> {
+        let mut found: Vec<(OrderedFloat<f32>, usize)> = vec![];
+        if k == 0 || self.positions.is_empty() {
+            return vec![];
+        }
+
+        // Rings that miss the grid hold nothing, so the search starts at the
+        // first one reaching it and ends at the one around all of it.
+        let (column, row) = self.cell(point);
+        let (last_column, last_row) = (self.columns as isize - 1, self.rows as isize - 1);
+        let ring_min = column
+            .saturating_neg()
+            .max(column.saturating_sub(last_column))
+            .max(row.saturating_neg())
+            .max(row.saturating_sub(last_row))
+            .max(0);
+        let ring_max = column
+            .saturating_abs()
+            .max(last_column.saturating_sub(column))
+            .max(row.saturating_abs())
+            .max(last_row.saturating_sub(row));
+
+        for ring in ring_min..=ring_max {
+            for (c, r) in self.ring_cells(column, row, ring) {
+                found.extend(
+                    self.cell_entries(c, r)
+                        .iter()
+                        .filter(|&&index| filter(index))
+                        .map(|&index| (OrderedFloat(self.positions[index].distance(point)), index)),
+                );
+            }
+            if found.len() >= k {
+                found.sort_unstable();
+                found.truncate(k);
+                if found[k - 1].0 .0 <= ring as f32 * self.cell_size {
+                    break;
+                }
+            }
+        }
+
+        found.sort_unstable();
+        found.truncate(k);
+        found.into_iter().map(|(_, index)| index).collect()
+    }
+
+    fn cell(&self, point: Vec2) -> (isize, isize) {
+        let cell = ((point - self.min) / self.cell_size).floor();
+        (cell.x as isize, cell.y as isize)
+    }
+
+    fn cell_entries(&self, column: isize, row: isize) -> &[usize] {
+        let cell = row as usize * self.columns + column as usize;
+        &self.entries[self.cell_starts[cell]..self.cell_starts[cell + 1]]
+    }
+
+    /// The cells of the grid `ring` cells away from `column`, `row`, the
+    /// border of the square clipped to the grid.
+    fn ring_cells(
+        &self,
+        column: isize,
+        row: isize,
+        ring: isize,
+    ) -> impl Iterator<Item = (isize, isize)> {
+        let (last_column, last_row) = (self.columns as isize - 1, self.rows as isize - 1);
+        let rows = row.saturating_sub(ring).max(0)..=row.saturating_add(ring).min(last_row);
+        rows.flat_map(move |r| {
+            // Whole rows at the top and bottom, the two sides in between.
+            let (columns, step) = if (r - row).abs() == ring {
+                (
+                    column.saturating_sub(ring).max(0)
+                        ..=column.saturating_add(ring).min(last_column),
+                    1,
+                )
+            } else {
+                (
+                    column.saturating_sub(ring)..=column.saturating_add(ring),
+                    2 * ring as usize,
+                )
+            };
+            columns
+                .step_by(step)
+                .filter(move |&c| c >= 0 && c <= last_column)
+                .map(move |c| (c, r))
+        })
+    }
+}
+
+fn bounds(positions: &[Vec2]) -> (Vec2, Vec2) {
+    if positions.is_empty() {
+        return (Vec2::ZERO, Vec2::ZERO);
+    }
+    positions.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), position| (min.min(*position), max.max(*position)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::{random_point_in_radius, seeded};
+    use nannou::rand::Rng;
+
+    fn brute_nearest_k(positions: &[Vec2], point: Vec2, k: usize) -> Vec<f32> {
+        let mut distances = positions
+            .iter()
+            .map(|position| position.distance(point))
+            .collect::<Vec<f32>>();
+        distances.sort_by(f32::total_cmp);
+        distances.truncate(k);
+        distances
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut rng = seeded(1);
+        let positions = (0..500)
+            .map(|_| random_point_in_radius(&mut rng, &Vec2::ZERO, 1000.))
+            .collect::<Vec<Vec2>>();
+        let grid = Grid::new(positions.iter().copied());
+        // Queries far outside the grid too, the search must reach back in.
+        for _ in 0..200 {
+            let point = random_point_in_radius(&mut rng, &Vec2::ZERO, 3000.);
+            for k in [1, 5, 20] {
+                let found = grid
+                    .nearest_k(point, k)
+                    .into_iter()
+                    .map(|i| positions[i].distance(point))
+                    .collect::<Vec<f32>>();
+                assert_eq!(found, brute_nearest_k(&positions, point, k));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_excluding_skips_the_point_itself() {
+        let mut rng = seeded(2);
+        let positions = (0..200)
+            .map(|_| random_point_in_radius(&mut rng, &Vec2::ZERO, 500.))
+            .collect::<Vec<Vec2>>();
+        let grid = Grid::new(positions.iter().copied());
+        for (i, &position) in positions.iter().enumerate() {
+            let nearest = grid.nearest_excluding(position, i).unwrap();
+            assert_ne!(nearest, i);
+            let others = positions
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, other)| *other)
+                .collect::<Vec<Vec2>>();
+            assert_eq!(
+                positions[nearest].distance(position),
+                brute_nearest_k(&others, position, 1)[0]
+            );
+        }
+    }
+
+    #[test]
+    fn within_radius_matches_brute_force() {
+        let mut rng = seeded(3);
+        let positions = (0..500)
+            .map(|_| random_point_in_radius(&mut rng, &Vec2::ZERO, 1000.))
+            .collect::<Vec<Vec2>>();
+        let grid = Grid::with_cell_size(positions.iter().copied(), 37.);
+        for _ in 0..100 {
+            let point = random_point_in_radius(&mut rng, &Vec2::ZERO, 1500.);
+            let radius = rng.gen::<f32>() * 400.;
+            let mut found = grid.within_radius(point, radius);
+            found.sort();
+            let expected = (0..positions.len())
+                .filter(|&i| positions[i].distance(point) <= radius)
+                .collect::<Vec<usize>>();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn empty_grid_finds_nothing() {
+        let grid = Grid::new(vec![]);
+        assert!(grid.is_empty());
+        assert_eq!(grid.nearest(Vec2::ZERO), None);
+        assert!(grid.within_radius(Vec2::ZERO, 100.).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::render::Canvas;
+use nannou::color::{IntoLinSrgba, LinSrgba, Srgba};
+use nannou::geom::Rect;
+use nannou::prelude::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Records what a sketch draws as SVG elements, one per shape, in drawing
+/// order. Starts with an opaque black background like `Raster`.
+#[derive(Clone, Debug)]
+pub struct Svg {
+    view: Rect,
+    elements: Vec<String>,
+}
+
+impl Svg {
+    pub fn new(view: Rect) -> Self {
+        let mut svg = Svg {
+            view,
+            elements: vec![],
+        };
+        svg.background(BLACK);
+        svg
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.view.w(),
+            h = self.view.h(),
+        );
+        for element in self.elements.iter() {
+            svg += "  ";
+            svg += element;
+            svg += "\n";
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("failed to create svg directory");
+        }
+        fs::write(path, self.to_svg()).expect("failed to write svg");
+    }
+
+    fn to_svg_space(&self, point: Vec2) -> Vec2 {
+        vec2(point.x - self.view.left(), self.view.top() - point.y)
+    }
+}
+
+/// `#rrggbb` and the alpha, for `fill`/`stroke` and their opacity.
+pub fn hex(color: LinSrgba) -> (String, f32) {
+    let color = Srgba::from_linear(color);
+    let channel = |c: f32| (c.clamp(0., 1.) * 255.).round() as u8;
+    (
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue)
+        ),
+        color.alpha,
+    )
+}
+
+fn fill(color: impl IntoLinSrgba<f32>) -> String {
+    let (hex, alpha) = hex(color.into_lin_srgba());
+    let mut fill = format!("fill=\"{}\"", hex);
+    if alpha < 1. {
+        write!(fill, " fill-opacity=\"{:.4}\"", alpha).unwrap();
+    }
+    fill
+}
+
+fn stroke(color: impl IntoLinSrgba<f32>, weight: f32) -> String {
+    let (hex, alpha) = hex(color.into_lin_srgba());
+    let mut stroke = format!("stroke=\"{}\" stroke-width=\"{}\"", hex, weight);
+    if alpha < 1. {
+        write!(stroke, " stroke-opacity=\"{:.4}\"", alpha).unwrap();
+    }
+    stroke
+}
+
+impl Canvas for Svg {
+    fn bounds(&self) -> Rect {
+        self.view
+    }
+
+    fn background(&mut self, color: impl IntoLinSrgba<f32>) {
+        self.elements.clear();
+        let view = self.view;
+        self.rect(view, color);
+    }
+
+    fn rect(&mut self, rect: Rect, color: impl IntoLinSrgba<f32>) {
+        let top_left = self.to_svg_space(rect.top_left());
+        self.elements.push(format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+            top_left.x,
+            top_left.y,
+            rect.w(),
+            rect.h(),
+            fill(color)
+        ));
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        let start = self.to_svg_space(start);
+        let end = self.to_svg_space(end);
+        self.elements.push(format!(
+            "<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" {} stroke-linecap=\"round\"/>",
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            stroke(color, weight)
+        ));
+    }
+
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: impl IntoLinSrgba<f32>) {
+        let center = self.to_svg_space(center);
+        self.elements.push(format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+            center.x,
+            center.y,
+            radius.abs(),
+            fill(color)
+        ));
+    }
+
+    fn ellipse_outline(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let center = self.to_svg_space(center);
+        self.elements.push(format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"none\" {}/>",
+            center.x,
+            center.y,
+            radius.abs(),
+            stroke(color, weight)
+        ));
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: impl IntoLinSrgba<f32>) {
+        let mut path = String::new();
+        for point in points {
+            let point = self.to_svg_space(*point);
+            write!(path, "{:.2},{:.2} ", point.x, point.y).unwrap();
+        }
+        self.elements.push(format!(
+            "<polygon points=\"{}\" {}/>",
+            path.trim_end(),
+            fill(color)
+        ));
+    }
+
+    fn region(&mut self, rings: &[Vec<Vec2>], color: impl IntoLinSrgba<f32>) {
+        let mut path = String::new();
+        for ring in rings.iter().filter(|ring| ring.len() >= 3) {
+            for (i, point) in ring.iter().enumerate() {
+                let point = self.to_svg_space(*point);
+                let command = if i == 0 { 'M' } else { 'L' };
+                write!(path, "{}{:.2},{:.2} ", command, point.x, point.y).unwrap();
+            }
+            path.push_str("Z ");
+        }
+        self.elements.push(format!(
+            "<path d=\"{}\" fill-rule=\"evenodd\" {}/>",
+            path.trim_end(),
+            fill(color)
+        ));
+    }
+
+    /// SVG has no per vertex colors, the triangle gets their mean.
+    fn triangle(&mut self, corners: [Vec2; 3], colors: [LinSrgba; 3]) {
+        let mut mean = LinSrgba::new(0., 0., 0., 0.);
+        for color in colors {
+            mean.red += color.red / 3.;
+            mean.green += color.green / 3.;
+            mean.blue += color.blue / 3.;
+            mean.alpha += color.alpha / 3.;
+        }
+        self.polygon(&corners, mean);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::args::Args;
+use crate::params::Validate;
+use crate::poster::Poster;
+use crate::render::Raster;
+use nannou::geom::Rect;
+use nannou::image::{Rgba, RgbaImage};
+use nannou::text::{font, rt, Font, Scale};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process;
+
+/// One parameter to vary, from `--sweep <key>=<from>:<to>:<count>` or
+/// `--sweep <key>=<a>,<b>,<c>`. Keys are the names in the parameter file,
+/// `background.3` picks an array element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    pub key: String,
+    pub values: Vec<f64>,
+}
+
+impl Sweep {
+    pub fn parse(sweep: &str) -> Option<Self> {
+        let (key, values) = sweep.split_once('=')?;
+        let values = if values.contains(':') {
+            let mut range = values.split(':');
+            let from: f64 = range.next()?.parse().ok()?;
+            let to: f64 = range.next()?.parse().ok()?;
+            let count: usize = range.next()?.parse().ok()?;
+            if range.next().is_some() || count == 0 {
+                return None;
+            }
+            (0..count)
+                .map(|i| match count {
+                    1 => from,
+                    _ => from + (to - from) * i as f64 / (count - 1) as f64,
+                })
+                .collect()
+        } else {
+            values
+                .split(',')
+                .map(|value| value.parse().ok())
+                .collect::<Option<Vec<f64>>>()?
+        };
+        Some(Sweep {
+            key: key.to_owned(),
+            values,
+        })
+    }
+}
+
+/// `params` with `key` set to `value`, rounded for whole number parameters.
+pub fn with_value<P: Serialize + DeserializeOwned>(
+    params: &P,
+    key: &str,
+    value: f64,
+) -> Result<P, String> {
+    let mut root = toml::Value::try_from(params).map_err(|error| error.to_string())?;
+    let mut target = &mut root;
+    for part in key.split('.') {
+        target = match target {
+            toml::Value::Table(table) => table.get_mut(part),
+            toml::Value::Array(array) => part.parse().ok().and_then(|i: usize| array.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("no parameter {}", key))?;
+    }
+    *target = match target {
+        toml::Value::Integer(_) => toml::Value::Integer(value.round() as i64),
+        toml::Value::Float(_) => toml::Value::Float(value),
+        _ => return Err(format!("{} is not a number", key)),
+    };
+    root.try_into().map_err(|error| error.to_string())
+}
+
+/// Renders every combination of up to two sweeps with the same seed and
+/// lays them out as a labelled grid, the first sweep across and the second
+/// down.
+#[derive(Clone, Debug)]
+pub struct ContactSheet {
+    pub path: PathBuf,
+    pub sweeps: Vec<Sweep>,
+    /// Simulation steps run before drawing.
+    pub steps: u64,
+    /// Frames drawn after the steps, more let trails build up.
+    pub frames: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+const LABEL_HEIGHT: u32 = 24;
+const GAP: u32 = 8;
+
+impl ContactSheet {
+    /// Set up when `--sweep` was given together with `--render`. Cells are
+    /// `--size`, 480x270 by default, and show the whole window. Sweeps past
+    /// the second are ignored, `Args::parse` already turns them down.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        if args.sweeps.is_empty() {
+            return None;
+        }
+        let (width, height) = args.size.unwrap_or((480, 270));
+        Some(ContactSheet {
+            path: args.render.clone()?,
+            sweeps: args.sweeps.iter().take(2).cloned().collect(),
+            steps: args.steps.unwrap_or(1200),
+            frames: args.frames.unwrap_or(1),
+            width,
+            height,
+        })
+    }
+
+    pub fn columns(&self) -> usize {
+        self.sweeps[0].values.len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.sweeps.get(1).map_or(1, |sweep| sweep.values.len())
+    }
+
+    /// The label and parameters of every cell, row by row, or why a sweep
+    /// doesn't fit the parameters or makes them invalid.
+    pub fn cells<P: Clone + Serialize + DeserializeOwned + Validate>(
+        &self,
+        base: &P,
+    ) -> Result<Vec<(String, P)>, String> {
+        let mut cells = vec![];
+        for row in 0..self.rows() {
+            for column in 0..self.columns() {
+                let mut params = base.clone();
+                let mut label = vec![];
+                for (sweep, i) in self.sweeps.iter().zip([column, row]) {
+                    let value = sweep.values[i];
+                    params = with_value(&params, &sweep.key, value)
+                        .and_then(Validate::validate)
+                        .map_err(|error| format!("--sweep {}: {}", sweep.key, error))?;
+                    label.push(format!("{} {}", sweep.key, format_value(value)));
+                }
+                cells.push((label.join("  "), params));
+            }
+        }
+        Ok(cells)
+    }
+
+    /// A raster of one cell, fitting the window the sketches are made for.
+    pub fn raster(&self) -> Raster {
+        let (window_width, window_height) = Poster::WINDOW;
+        let scale = (self.width as f32 / window_width).min(self.height as f32 / window_height);
+        Raster::new(
+            self.width,
+            self.height,
+            Rect::from_w_h(self.width as f32 / scale, self.height as f32 / scale),
+        )
+    }
+
+    /// Calls `render` for every cell with its parameters and a fresh raster,
+    /// then saves the sheet with `title` on top. Exits if a sweep doesn't
+    /// fit the parameters or makes them invalid.
+    pub fn run<P: Clone + Serialize + DeserializeOwned + Validate>(
+        &self,
+        title: &str,
+        base: &P,
+        mut render: impl FnMut(P, &mut Raster),
+    ) {
+        let font = font::default_notosans();
+        let cell_height = self.height + LABEL_HEIGHT;
+        let mut sheet = RgbaImage::from_pixel(
+            GAP + self.columns() as u32 * (self.width + GAP),
+            LABEL_HEIGHT + GAP + self.rows() as u32 * (cell_height + GAP),
+            Rgba([24, 24, 24, 255]),
+        );
+        label(&mut sheet, &font, GAP, 4, title);
+
+        let cells = self.cells(base).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(2);
+        });
+        let count = cells.len();
+        for (i, (text, params)) in cells.into_iter().enumerate() {
+            eprintln!("cell {}/{} {}", i + 1, count, text);
+            let mut raster = self.raster();
+            render(params, &mut raster);
+            let x = GAP + (i % self.columns()) as u32 * (self.width + GAP);
+            let y = LABEL_HEIGHT + GAP + (i / self.columns()) as u32 * (cell_height + GAP);
+            nannou::image::imageops::replace(&mut sheet, &raster.to_image(), x, y);
+            label(&mut sheet, &font, x, y + self.height + 4, &text);
+        }
+
+        sheet
+            .save(&self.path)
+            .expect("failed to save contact sheet");
+        println!("rendered {}", self.path.display());
+    }
+}
+
+fn format_value(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// Draws `text` in light grey with its top left corner at `x`, `y`.
+fn label(image: &mut RgbaImage, font: &Font, x: u32, y: u32, text: &str) {
+    let scale = Scale::uniform(15.);
+    let ascent = font.v_metrics(scale).ascent;
+    for glyph in font.layout(text, scale, rt::point(x as f32, y as f32 + ascent)) {
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        glyph.draw(|gx, gy, coverage| {
+            let px = bounds.min.x + gx as i32;
+            let py = bounds.min.y + gy as i32;
+            if px < 0 || py < 0 || px as u32 >= image.width() || py as u32 >= image.height() {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                let background = pixel[channel] as f32;
+                pixel[channel] = (background + (220. - background) * coverage).round() as u8;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Params {
+        count: usize,
+        speed: f32,
+        background: [f32; 4],
+        name: String,
+    }
+
+    impl Validate for Params {
+        fn validate(self) -> Result<Self, String> {
+            match self.count {
+                0 => Err("count must be at least 1".to_owned()),
+                _ => Ok(self),
+            }
+        }
+    }
+
+    fn params() -> Params {
+        Params {
+            count: 10,
+            speed: 0.5,
+            background: [0., 0., 0., 1.],
+            name: "params".to_owned(),
+        }
+    }
+
+    #[test]
+    fn parse_range() {
+        let sweep = Sweep::parse("speed=0:1:5").unwrap();
+        assert_eq!(sweep.key, "speed");
+        assert_eq!(sweep.values, [0., 0.25, 0.5, 0.75, 1.]);
+        assert_eq!(Sweep::parse("speed=2:3:1").unwrap().values, [2.]);
+    }
+
+    #[test]
+    fn parse_list() {
+        let sweep = Sweep::parse("background.3=0.1,0.5,1").unwrap();
+        assert_eq!(sweep.key, "background.3");
+        assert_eq!(sweep.values, [0.1, 0.5, 1.]);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_sweeps() {
+        for sweep in [
+            "speed",
+            "speed=0:1",
+            "speed=0:1:0",
+            "speed=0:1:2:3",
+            "speed=a,b",
+        ] {
+            assert_eq!(Sweep::parse(sweep), None, "{}", sweep);
+        }
+    }
+
+    #[test]
+    fn with_value_sets_nested_keys() {
+        let swept = with_value(&params(), "background.3", 0.25).unwrap();
+        assert_eq!(swept.background, [0., 0., 0., 0.25]);
+        assert_eq!(with_value(&params(), "speed", 2.).unwrap().speed, 2.);
+    }
+
+    #[test]
+    fn with_value_rounds_whole_numbers() {
+        assert_eq!(with_value(&params(), "count", 2.6).unwrap().count, 3);
+    }
+
+    #[test]
+    fn with_value_rejects_unknown_and_non_numeric_keys() {
+        assert!(with_value(&params(), "missing", 1.).is_err());
+        assert!(with_value(&params(), "background.4", 1.).is_err());
+        assert!(with_value(&params(), "name", 1.).is_err());
+    }
+
+    fn sheet(sweeps: &[&str]) -> ContactSheet {
+        ContactSheet {
+            path: PathBuf::from("sheet.png"),
+            sweeps: sweeps
+                .iter()
+                .map(|sweep| Sweep::parse(sweep).unwrap())
+                .collect(),
+            steps: 0,
+            frames: 1,
+            width: 48,
+            height: 27,
+        }
+    }
+
+    #[test]
+    fn cells_go_row_by_row() {
+        let cells = sheet(&["count=1:3:3", "speed=0,1"])
+            .cells(&params())
+            .unwrap();
+        let labels = cells
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels[1], "count 2  speed 0");
+        assert_eq!(labels[3], "count 1  speed 1");
+        assert_eq!(cells[5].1.count, 3);
+        assert_eq!(cells[5].1.speed, 1.);
+    }
+
+    #[test]
+    fn cells_reject_invalid_params() {
+        assert_eq!(
+            sheet(&["count=0:2:3"]).cells(&params()).unwrap_err(),
+            "--sweep count: count must be at least 1"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::render::{to_u8, Canvas, Raster};
+use nannou::color::{IntoLinSrgba, LinSrgba, Srgba};
+use nannou::geom::Rect;
+use nannou::prelude::*;
+
+/// How trails fade and are shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+    /// Simulated seconds for trails to fade to half their brightness.
+    pub half_life: f32,
+    /// Brightness multiplier applied before tonemapping.
+    pub exposure: f32,
+    pub tonemap: Tonemap,
+    /// Shown where nothing was drawn.
+    pub background: LinSrgba,
+}
+
+impl Fade {
+    pub fn new(half_life: f32, background: impl IntoLinSrgba<f32>) -> Self {
+        Fade {
+            half_life,
+            exposure: 1.,
+            tonemap: Tonemap::Clamp,
+            background: background.into_lin_srgba(),
+        }
+    }
+}
+
+/// Maps the linear brightness of the trails to what a pixel can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemap {
+    /// Cuts off at full brightness.
+    Clamp,
+    /// `x / (1 + x)`, highlights roll off and never clip.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, more contrast than Reinhard.
+    Aces,
+}
+
+impl Tonemap {
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            Tonemap::Clamp => x.clamp(0., 1.),
+            Tonemap::Reinhard => x.max(0.) / (1. + x.max(0.)),
+            Tonemap::Aces => {
+                let x = x.max(0.);
+                ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0., 1.)
+            }
+        }
+    }
+}
+
+/// A persistence layer. Shapes are blended into a floating point buffer that
+/// fades exponentially with simulated time and is tonemapped over the
+/// background when shown, instead of fading by drawing the background
+/// translucently over every frame. Trails fade out completely and look the
+/// same at any frame rate.
+#[derive(Clone, Debug)]
+pub struct Trails {
+    pub fade: Fade,
+    buffer: Raster,
+    time: Option<f64>,
+    /// How many reference frames the shapes drawn now stand for.
+    frames: f32,
+}
+
+impl Trails {
+    /// Empty trails of `width` x `height` pixels showing `view`.
+    pub fn new(fade: Fade, width: u32, height: u32, view: Rect) -> Self {
+        let mut buffer = Raster::new(width, height, view);
+        buffer.background(LinSrgba::new(0., 0., 0., 0.));
+        Trails {
+            fade,
+            buffer,
+            time: None,
+            frames: 1.,
+        }
+    }
+
+    /// Empty trails the size of `target`, showing what it shows.
+    pub fn matching(fade: Fade, target: &Raster) -> Self {
+        let mut trails = Self::new(fade, target.width(), target.height(), target.view());
+        trails.buffer.set_bounds(target.bounds());
+        trails
+    }
+
+    pub fn matches(&self, target: &Raster) -> bool {
+        self.fits(target.width(), target.height(), target.view())
+            && self.buffer.bounds() == target.bounds()
+    }
+
+    /// Whether the trails are `width` x `height` pixels showing `view`.
+    pub fn fits(&self, width: u32, height: u32, view: Rect) -> bool {
+        self.buffer.width() == width && self.buffer.height() == height && self.buffer.view() == view
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.background(LinSrgba::new(0., 0., 0., 0.));
+    }
+
+    /// Fades by the simulated time since the last call, to `time`. Shapes
+    /// drawn afterwards are as opaque as their alpha if a frame is `reference`
+    /// seconds long and build up to the same opacity over the same time at
+    /// any other frame length.
+    pub fn advance(&mut self, time: f64, reference: f32) {
+        let elapsed = self.time.map_or(0., |last| (time - last).max(0.) as f32);
+        self.time = Some(time);
+        if elapsed == 0. {
+            return;
+        }
+        self.frames = elapsed / reference;
+        let factor = 0.5f32.powf(elapsed / self.fade.half_life.max(f32::EPSILON));
+        for pixel in self.buffer.pixels_mut() {
+            pixel.red *= factor;
+            pixel.green *= factor;
+            pixel.blue *= factor;
+            pixel.alpha *= factor;
+        }
+    }
+
+    /// Tonemaps the trails over the background into `target`, which has to
+    /// match them.
+    pub fn resolve(&self, target: &mut Raster) {
+        for (out, pixel) in target.pixels_mut().iter_mut().zip(self.buffer.pixels()) {
+            *out = self.resolve_pixel(*pixel);
+        }
+    }
+
+    /// Like `resolve` into 8 bit sRGB RGBA rows, reusing the allocation of
+    /// `bytes`, for uploading to a texture every frame.
+    pub fn resolve_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.clear();
+        bytes.extend(self.buffer.pixels().iter().flat_map(|pixel| {
+            let color = Srgba::from_linear(self.resolve_pixel(*pixel));
+            [color.red, color.green, color.blue, color.alpha].map(to_u8)
+        }));
+    }
+
+    fn resolve_pixel(&self, pixel: LinSrgba) -> LinSrgba {
+        let fade = self.fade;
+        let background = fade.background;
+        let map = |channel: f32| fade.tonemap.apply(channel * fade.exposure);
+        let cover = 1. - pixel.alpha.clamp(0., 1.);
+        LinSrgba::new(
+            map(pixel.red) + background.red * cover,
+            map(pixel.green) + background.green * cover,
+            map(pixel.blue) + background.blue * cover,
+            1.,
+        )
+    }
+
+    /// The resolved trails as a new raster.
+    pub fn to_raster(&self) -> Raster {
+        let mut raster = Raster::new(
+            self.buffer.width(),
+            self.buffer.height(),
+            self.buffer.view(),
+        );
+        raster.set_bounds(self.buffer.bounds());
+        self.resolve(&mut raster);
+        raster
+    }
+
+    /// `color` with its alpha built up over `frames` reference frames.
+    fn deposit(&self, color: impl IntoLinSrgba<f32>) -> LinSrgba {
+        let mut color = color.into_lin_srgba();
+        color.alpha = 1. - (1. - color.alpha.clamp(0., 1.)).powf(self.frames);
+        color
+    }
+}
+
+impl Canvas for Trails {
+    fn bounds(&self) -> Rect {
+        self.buffer.bounds()
+    }
+
+    fn background(&mut self, color: impl IntoLinSrgba<f32>) {
+        self.buffer.background(color);
+    }
+
+    fn rect(&mut self, rect: Rect, color: impl IntoLinSrgba<f32>) {
+        let color = self.deposit(color);
+        self.buffer.rect(rect, color);
+    }
+
+    fn line(&mut self, start: Vec2, end: Vec2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        let color = self.deposit(color);
+        self.buffer.line(start, end, weight, color);
+    }
+
+    fn ellipse(&mut self, center: Vec2, radius: f32, color: impl IntoLinSrgba<f32>) {
+        let color = self.deposit(color);
+        self.buffer.ellipse(center, radius, color);
+    }
+
+    fn ellipse_outline(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let color = self.deposit(color);
+        self.buffer.ellipse_outline(center, radius, weight, color);
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: impl IntoLinSrgba<f32>) {
+        let color = self.deposit(color);
+        self.buffer.polygon(points, color);
+    }
+
+    fn region(&mut self, rings: &[Vec<Vec2>], color: impl IntoLinSrgba<f32>) {
+        let color = self.deposit(color);
+        self.buffer.region(rings, color);
+    }
+
+    fn triangle(&mut self, corners: [Vec2; 3], colors: [LinSrgba; 3]) {
+        let colors = colors.map(|color| self.deposit(color));
+        self.buffer.triangle(corners, colors);
+    }
+}
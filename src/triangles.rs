@@ -0,0 +1,225 @@
+use crate::gui::Ui;
+use crate::links::LinkTracker;
+use crate::presets::Lerp;
+use crate::render::Canvas;
+use delaunator::Triangulation;
+use nannou::color::{Gradient, Hsla, IntoLinSrgba};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What picks a triangle's place on the gradient.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriangleColor {
+    /// Large triangles at the end of the gradient.
+    #[default]
+    Area,
+    /// Slivers at the start, equilateral triangles at the end.
+    Aspect,
+    /// The direction of the longest side, once around the gradient per half
+    /// turn.
+    Orientation,
+    /// The mean age of the links along the sides.
+    Age,
+}
+
+impl TriangleColor {
+    pub fn name(self) -> &'static str {
+        match self {
+            TriangleColor::Area => "area",
+            TriangleColor::Aspect => "aspect",
+            TriangleColor::Orientation => "orientation",
+            TriangleColor::Age => "age",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TriangleColor::Area => TriangleColor::Aspect,
+            TriangleColor::Aspect => TriangleColor::Orientation,
+            TriangleColor::Orientation => TriangleColor::Age,
+            TriangleColor::Age => TriangleColor::Area,
+        }
+    }
+}
+
+/// How a sketch fills the Delaunay triangles, if at all.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriangleSettings {
+    pub filled: bool,
+    pub color: TriangleColor,
+    /// Blends the colors of the corners across each triangle instead of
+    /// filling it flat, for smooth shading.
+    pub interpolate: bool,
+    /// Seconds of link age at the end of the gradient.
+    pub age: f32,
+    /// Multiplies the gradient's alpha.
+    pub opacity: f32,
+}
+
+impl Default for TriangleSettings {
+    fn default() -> Self {
+        TriangleSettings {
+            filled: false,
+            color: TriangleColor::Area,
+            interpolate: false,
+            age: 10.,
+            opacity: 0.5,
+        }
+    }
+}
+
+impl TriangleSettings {
+    /// Where each triangle of `triangulation` lies on the gradient, from 0 to
+    /// 1. Ages come from `links`, sides without a link count as new.
+    pub fn values(
+        &self,
+        points: &[Vec2],
+        triangulation: &Triangulation,
+        links: &LinkTracker,
+        now: f64,
+    ) -> Vec<f32> {
+        let triangles = triangulation.triangles.chunks_exact(3);
+        let mean_area = triangles
+            .clone()
+            .map(|t| area([points[t[0]], points[t[1]], points[t[2]]]))
+            .sum::<f32>()
+            / triangles.len() as f32;
+        triangles
+            .map(|t| {
+                let corners = [points[t[0]], points[t[1]], points[t[2]]];
+                let value = match self.color {
+                    TriangleColor::Area => area(corners) / mean_area / 2.,
+                    TriangleColor::Aspect => quality(corners),
+                    TriangleColor::Orientation => {
+                        let side = longest_side(corners);
+                        side.y.atan2(side.x).rem_euclid(PI) / PI
+                    }
+                    TriangleColor::Age => {
+                        let ages = [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]
+                            .map(|(a, b)| links.get(a, b).map_or(0., |link| link.age(now)));
+                        ages.iter().sum::<f32>() / 3. / self.age.max(f32::EPSILON)
+                    }
+                };
+                value.clamp(0., 1.)
+            })
+            .collect()
+    }
+
+    /// Fills every triangle with the gradient's color at its value, or when
+    /// interpolating, blends the corners' colors, each at the mean value of
+    /// the triangles around it.
+    pub fn draw(
+        &self,
+        canvas: &mut impl Canvas,
+        points: &[Vec2],
+        triangulation: &Triangulation,
+        values: &[f32],
+        gradient: &Gradient<Hsla>,
+    ) {
+        if !self.filled {
+            return;
+        }
+        let color = |value: f32| {
+            let mut color = gradient.get(value);
+            color.alpha *= self.opacity;
+            color.into_lin_srgba()
+        };
+        let triangles = triangulation.triangles.chunks_exact(3);
+
+        let mut corner_values = vec![(0., 0); points.len()];
+        if self.interpolate {
+            for (t, value) in triangles.clone().zip(values) {
+                for &corner in t {
+                    corner_values[corner].0 += value;
+                    corner_values[corner].1 += 1;
+                }
+            }
+        }
+
+        for (t, &value) in triangles.zip(values) {
+            let corners = [points[t[0]], points[t[1]], points[t[2]]];
+            let colors = if self.interpolate {
+                [t[0], t[1], t[2]].map(|corner| {
+                    let (sum, count) = corner_values[corner];
+                    color(sum / count as f32)
+                })
+            } else {
+                [color(value); 3]
+            };
+            canvas.triangle(corners, colors);
+        }
+    }
+
+    /// Buttons for mode and colouring and sliders for the rest. True if
+    /// anything changed.
+    pub fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let mode = match (self.filled, self.interpolate) {
+            (false, _) => "triangles: off",
+            (true, false) => "triangles: flat",
+            (true, true) => "triangles: smooth",
+        };
+        if ui.button(mode) {
+            (self.filled, self.interpolate) = match (self.filled, self.interpolate) {
+                (false, _) => (true, false),
+                (true, false) => (true, true),
+                (true, true) => (false, false),
+            };
+            changed = true;
+        }
+        if !self.filled {
+            return changed;
+        }
+        if ui.button(&format!("triangle color: {}", self.color.name())) {
+            self.color = self.color.next();
+            changed = true;
+        }
+        if self.color == TriangleColor::Age {
+            changed |= ui.slider("triangle age", &mut self.age, 0.0..=60.);
+        }
+        changed |= ui.slider("triangle opacity", &mut self.opacity, 0.0..=1.);
+        changed
+    }
+}
+
+/// Switches modes and colouring half way, the rest blends.
+impl Lerp for TriangleSettings {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let half = if t < 0.5 { self } else { to };
+        TriangleSettings {
+            filled: half.filled,
+            color: half.color,
+            interpolate: half.interpolate,
+            age: self.age.lerp(&to.age, t),
+            opacity: self.opacity.lerp(&to.opacity, t),
+        }
+    }
+}
+
+pub fn area([a, b, c]: [Vec2; 3]) -> f32 {
+    (b - a).perp_dot(c - a).abs() / 2.
+}
+
+/// Twice the inradius over the circumradius, 1 for equilateral triangles
+/// and 0 for degenerate ones.
+pub fn quality(corners: [Vec2; 3]) -> f32 {
+    let [a, b, c] = corners;
+    let sides = [a.distance(b), b.distance(c), c.distance(a)];
+    let product = sides[0] * sides[1] * sides[2];
+    if product == 0. {
+        return 0.;
+    }
+    let area = area(corners);
+    let perimeter = sides.iter().sum::<f32>();
+    // inradius = 2 area / perimeter, circumradius = product / (4 area)
+    16. * area * area / (perimeter * product)
+}
+
+fn longest_side([a, b, c]: [Vec2; 3]) -> Vec2 {
+    [b - a, c - b, a - c]
+        .into_iter()
+        .max_by(|x, y| x.length_squared().total_cmp(&y.length_squared()))
+        .unwrap()
+}
@@ -0,0 +1,188 @@
+use crate::args::Args;
+use crate::render::Raster;
+use nannou::geom::Rect;
+use nannou::image::gif::{GifEncoder, Repeat};
+use nannou::image::{Delay, Frame, RgbaImage};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// YUV4MPEG2 with full chroma (`C444`), playable by mpv and ffmpeg.
+    Y4m,
+    /// Bare RGBA frames, for `ffmpeg -f rawvideo -pix_fmt rgba -s WxH -r FPS -i -`.
+    Raw,
+    /// Looping animated GIF, each frame quantised to its own palette.
+    Gif,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "y4m" => Some(Format::Y4m),
+            "raw" | "rgba" => Some(Format::Raw),
+            "gif" => Some(Format::Gif),
+            _ => None,
+        }
+    }
+
+    /// Guessed from the extension, `-` and unknown extensions are raw.
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::parse)
+            .unwrap_or(Format::Raw)
+    }
+}
+
+/// Renders a sketch into a sequence of frames without a window, advancing
+/// the simulation by `1 / fps` seconds per frame in whole steps, the same
+/// steps on every run.
+#[derive(Clone, Debug)]
+pub struct Video {
+    /// Output file, `-` writes to stdout.
+    pub path: PathBuf,
+    pub format: Format,
+    pub fps: u32,
+    pub duration: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Video {
+    /// Set up when `--video` was given, 10 seconds at 60 fps and 1920x1080
+    /// by default. GIFs default to 50 fps, their delays are whole
+    /// hundredths of a second and viewers slow down shorter ones.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let path = args.video.clone()?;
+        let (width, height) = args.size.unwrap_or((1920, 1080));
+        let format = args.format.unwrap_or_else(|| Format::from_path(&path));
+        let fps = match format {
+            Format::Gif => 50,
+            _ => 60,
+        };
+        Some(Video {
+            format,
+            path,
+            fps: args.fps.unwrap_or(fps),
+            duration: args.duration.unwrap_or(10.0),
+            width,
+            height,
+        })
+    }
+
+    pub fn frames(&self) -> u64 {
+        (self.duration * self.fps as f32).round() as u64
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps as f64)
+    }
+
+    /// Calls `frame` with the simulation time to advance before drawing,
+    /// then encodes the raster. Progress goes to stderr so stdout stays a
+    /// clean pipe.
+    pub fn run(&self, mut frame: impl FnMut(&mut Raster, Duration)) {
+        let mut raster = Raster::new(
+            self.width,
+            self.height,
+            Rect::from_w_h(self.width as f32, self.height as f32),
+        );
+        let mut encoder = Encoder::new(self).expect("failed to open video output");
+        let frames = self.frames();
+        for index in 0..frames {
+            frame(&mut raster, self.frame_duration());
+            encoder
+                .frame(&raster.to_image())
+                .expect("failed to encode frame");
+            if (index + 1) % self.fps as u64 == 0 {
+                eprintln!("frame {}/{}", index + 1, frames);
+            }
+        }
+        encoder.finish().expect("failed to finish video");
+        eprintln!("rendered {}", self.path.display());
+    }
+}
+
+enum Encoder {
+    Y4m(Box<dyn Write>),
+    Raw(Box<dyn Write>),
+    Gif(Box<GifEncoder<Box<dyn Write>>>, Delay),
+}
+
+impl Encoder {
+    fn new(video: &Video) -> io::Result<Self> {
+        let mut out = output(&video.path)?;
+        Ok(match video.format {
+            Format::Y4m => {
+                writeln!(
+                    out,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    video.width, video.height, video.fps
+                )?;
+                Encoder::Y4m(out)
+            }
+            Format::Raw => Encoder::Raw(out),
+            Format::Gif => {
+                let mut gif = GifEncoder::new_with_speed(out, 10);
+                gif.set_repeat(Repeat::Infinite).map_err(to_io)?;
+                let delay = Delay::from_numer_denom_ms(1000, video.fps);
+                Encoder::Gif(Box::new(gif), delay)
+            }
+        })
+    }
+
+    fn frame(&mut self, image: &RgbaImage) -> io::Result<()> {
+        match self {
+            Encoder::Y4m(out) => {
+                out.write_all(b"FRAME\n")?;
+                out.write_all(&yuv444(image))
+            }
+            Encoder::Raw(out) => out.write_all(image.as_raw()),
+            Encoder::Gif(gif, delay) => gif
+                .encode_frame(Frame::from_parts(image.clone(), 0, 0, *delay))
+                .map_err(to_io),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Encoder::Y4m(mut out) | Encoder::Raw(mut out) => out.flush(),
+            // The trailer is written when the encoder drops.
+            Encoder::Gif(gif, _) => {
+                drop(gif);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn output(path: &Path) -> io::Result<Box<dyn Write>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+fn to_io(error: nannou::image::ImageError) -> io::Error {
+    io::Error::other(error)
+}
+
+/// Planar Y, Cb, Cr with BT.601 limited range, which Y4M players assume.
+fn yuv444(image: &RgbaImage) -> Vec<u8> {
+    let plane = (image.width() * image.height()) as usize;
+    let mut yuv = vec![0; plane * 3];
+    for (i, pixel) in image.pixels().enumerate() {
+        let [r, g, b, _] = pixel.0;
+        let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+        yuv[i] = (16.0 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8;
+        yuv[plane + i] = (128.0 - 37.797 * r - 74.203 * g + 112.0 * b).round() as u8;
+        yuv[2 * plane + i] = (128.0 + 112.0 * r - 93.786 * g - 18.214 * b).round() as u8;
+    }
+    yuv
+}
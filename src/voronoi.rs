@@ -0,0 +1,391 @@
+use crate::gui::Ui;
+use crate::presets::Lerp;
+use crate::render::Canvas;
+use delaunator::{next_halfedge, Triangulation, EMPTY};
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Segments of the circle cells are clipped to.
+const CIRCLE_SEGMENTS: usize = 128;
+
+/// A point's Voronoi cell, a convex polygon in counterclockwise order.
+#[derive(Clone, Debug)]
+pub struct Cell {
+    /// Index of the point the cell belongs to.
+    pub site: usize,
+    pub polygon: Vec<Vec2>,
+}
+
+impl Cell {
+    pub fn area(&self) -> f32 {
+        signed_area(&self.polygon)
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut impl Canvas,
+        style: CellStyle,
+        weight: f32,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let color = color.into_lin_srgba();
+        match style {
+            CellStyle::Off => (),
+            CellStyle::Filled => canvas.polygon(&self.polygon, color),
+            CellStyle::Outlined => {
+                let next = self.polygon.iter().cycle().skip(1);
+                for (&start, &end) in self.polygon.iter().zip(next) {
+                    canvas.line(start, end, weight, color);
+                }
+            }
+        }
+    }
+}
+
+/// The Voronoi cells of `points`, which `triangulation` has to be made of,
+/// clipped to the disc of `radius` around `center`. Cells are walked around
+/// their point along the halfedges, their corners are the circumcenters of
+/// the triangles on the way. Cells of hull points are open, their two rays
+/// leave perpendicular to the hull. Points without a triangle, like
+/// duplicates, have no cell.
+pub fn cells(
+    points: &[Vec2],
+    triangulation: &Triangulation,
+    center: Vec2,
+    radius: f32,
+) -> Vec<Cell> {
+    let triangles = &triangulation.triangles;
+    let halfedges = &triangulation.halfedges;
+    let circumcenters = (0..triangles.len() / 3)
+        .map(|t| {
+            circumcenter(
+                points[triangles[3 * t]],
+                points[triangles[3 * t + 1]],
+                points[triangles[3 * t + 2]],
+            )
+        })
+        .collect::<Vec<Vec2>>();
+
+    // A halfedge into every point, the hull's for points on it, so walks
+    // around hull points start at one end of their open cell.
+    let mut inedges = vec![EMPTY; points.len()];
+    for e in 0..triangles.len() {
+        let point = triangles[next_halfedge(e)];
+        if halfedges[e] == EMPTY || inedges[point] == EMPTY {
+            inedges[point] = e;
+        }
+    }
+
+    let circle = (0..CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + vec2(angle.cos(), angle.sin()) * radius
+        })
+        .collect::<Vec<Vec2>>();
+
+    let mut cells = vec![];
+    for (site, &e0) in inedges.iter().enumerate() {
+        if e0 == EMPTY {
+            continue;
+        }
+        let mut corners = vec![];
+        let mut e = e0;
+        let last = loop {
+            corners.push(circumcenters[e / 3]);
+            let outgoing = next_halfedge(e);
+            e = halfedges[outgoing];
+            if e == EMPTY {
+                break Some(outgoing);
+            }
+            if e == e0 {
+                break None;
+            }
+        };
+
+        if let Some(outgoing) = last {
+            // Close the open cell far outside the disc.
+            let far = 4.
+                * corners
+                    .iter()
+                    .chain([&points[site]])
+                    .map(|corner| corner.distance(center) + radius)
+                    .fold(0., f32::max);
+            let incoming = outward_normal(points, triangulation, e0);
+            let outgoing = outward_normal(points, triangulation, outgoing);
+            let first = corners[0];
+            let end = *corners.last().unwrap();
+            let between = (incoming + outgoing).normalize_or_zero();
+            corners.push(end + outgoing * far);
+            corners.push(points[site] + between * 2. * far);
+            corners.push(first + incoming * far);
+        }
+
+        if signed_area(&corners) < 0. {
+            corners.reverse();
+        }
+        let polygon = if corners
+            .iter()
+            .all(|corner| corner.distance(center) <= radius)
+        {
+            corners
+        } else {
+            clip(&circle, &corners)
+        };
+        if polygon.len() >= 3 {
+            cells.push(Cell { site, polygon });
+        }
+    }
+    cells
+}
+
+pub fn circumcenter(a: Vec2, b: Vec2, c: Vec2) -> Vec2 {
+    let (b, c) = (b - a, c - a);
+    let d = 2. * b.perp_dot(c);
+    let (b2, c2) = (b.length_squared(), c.length_squared());
+    a + vec2(c.y * b2 - b.y * c2, b.x * c2 - c.x * b2) / d
+}
+
+/// Positive for counterclockwise polygons.
+pub fn signed_area(polygon: &[Vec2]) -> f32 {
+    let next = polygon.iter().cycle().skip(1);
+    polygon
+        .iter()
+        .zip(next)
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.
+}
+
+/// Unit normal of the hull halfedge `e`, pointing away from its triangle.
+fn outward_normal(points: &[Vec2], triangulation: &Triangulation, e: usize) -> Vec2 {
+    let a = points[triangulation.triangles[e]];
+    let b = points[triangulation.triangles[next_halfedge(e)]];
+    let opposite = points[triangulation.triangles[next_halfedge(next_halfedge(e))]];
+    let normal = (b - a).perp().normalize_or_zero();
+    if normal.dot(opposite - a) > 0. {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/// Sutherland-Hodgman, `subject` cut by every edge of the convex
+/// counterclockwise `clipper`.
+fn clip(subject: &[Vec2], clipper: &[Vec2]) -> Vec<Vec2> {
+    let mut polygon = subject.to_vec();
+    for (&a, &b) in clipper.iter().zip(clipper.iter().cycle().skip(1)) {
+        let edge = b - a;
+        let side = |point: Vec2| edge.perp_dot(point - a);
+        let input = std::mem::take(&mut polygon);
+        for (&p, &q) in input.iter().zip(input.iter().cycle().skip(1)) {
+            let (side_p, side_q) = (side(p), side(q));
+            if side_p >= 0. {
+                polygon.push(p);
+            }
+            if (side_p >= 0.) != (side_q >= 0.) {
+                polygon.push(p + (q - p) * (side_p / (side_p - side_q)));
+            }
+        }
+        if polygon.is_empty() {
+            break;
+        }
+    }
+    polygon
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellStyle {
+    #[default]
+    Off,
+    Filled,
+    Outlined,
+}
+
+impl CellStyle {
+    pub fn next(self) -> Self {
+        match self {
+            CellStyle::Off => CellStyle::Filled,
+            CellStyle::Filled => CellStyle::Outlined,
+            CellStyle::Outlined => CellStyle::Off,
+        }
+    }
+}
+
+/// What picks a cell's place on the gradient.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellColor {
+    /// Large cells at the end of the gradient.
+    #[default]
+    Area,
+    /// Time since the particle got its target.
+    Age,
+    /// The particle's distance to its target.
+    Target,
+}
+
+impl CellColor {
+    pub fn name(self) -> &'static str {
+        match self {
+            CellColor::Area => "area",
+            CellColor::Age => "age",
+            CellColor::Target => "target",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CellColor::Area => CellColor::Age,
+            CellColor::Age => CellColor::Target,
+            CellColor::Target => CellColor::Area,
+        }
+    }
+}
+
+/// How a sketch draws Voronoi cells, if at all.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellSettings {
+    pub style: CellStyle,
+    pub color: CellColor,
+    /// Of outlines.
+    pub weight: f32,
+    /// Multiplies the gradient's alpha.
+    pub opacity: f32,
+}
+
+impl Default for CellSettings {
+    fn default() -> Self {
+        CellSettings {
+            style: CellStyle::Off,
+            color: CellColor::Area,
+            weight: 2.,
+            opacity: 0.5,
+        }
+    }
+}
+
+impl CellSettings {
+    /// Buttons cycling style and colouring and sliders for the rest. True if
+    /// anything changed.
+    pub fn edit(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let style = match self.style {
+            CellStyle::Off => "cells: off",
+            CellStyle::Filled => "cells: filled",
+            CellStyle::Outlined => "cells: outlined",
+        };
+        if ui.button(style) {
+            self.style = self.style.next();
+            changed = true;
+        }
+        if self.style == CellStyle::Off {
+            return changed;
+        }
+        if ui.button(&format!("cell color: {}", self.color.name())) {
+            self.color = self.color.next();
+            changed = true;
+        }
+        if self.style == CellStyle::Outlined {
+            changed |= ui.slider("cell weight", &mut self.weight, 0.0..=20.);
+        }
+        changed |= ui.slider("cell opacity", &mut self.opacity, 0.0..=1.);
+        changed
+    }
+}
+
+/// Switches style and colouring half way, the rest blends.
+impl Lerp for CellSettings {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let half = if t < 0.5 { self } else { to };
+        CellSettings {
+            style: half.style,
+            color: half.color,
+            weight: self.weight.lerp(&to.weight, t),
+            opacity: self.opacity.lerp(&to.opacity, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::{random_point_in_radius, seeded};
+    use delaunator::{triangulate, Point};
+
+    const RADIUS: f32 = 1000.;
+
+    fn cells_of(seed: u64, spread: f32) -> (Vec<Vec2>, Vec<Cell>) {
+        let mut rng = seeded(seed);
+        let points = (0..200)
+            .map(|_| random_point_in_radius(&mut rng, &Vec2::ZERO, spread))
+            .collect::<Vec<Vec2>>();
+        let triangulation = triangulate(
+            &points
+                .iter()
+                .map(|point| Point {
+                    x: point.x as f64,
+                    y: point.y as f64,
+                })
+                .collect::<Vec<Point>>(),
+        );
+        let cells = cells(&points, &triangulation, Vec2::ZERO, RADIUS);
+        (points, cells)
+    }
+
+    #[test]
+    fn cells_tile_the_disc() {
+        let (points, cells) = cells_of(1, RADIUS * 0.9);
+        assert_eq!(cells.len(), points.len());
+        let area = cells.iter().map(Cell::area).sum::<f32>();
+        let n = CIRCLE_SEGMENTS as f32;
+        let disc = n / 2. * RADIUS * RADIUS * (TAU / n).sin();
+        assert!((area - disc).abs() < disc * 1e-4, "{} != {}", area, disc);
+    }
+
+    #[test]
+    fn cells_are_convex_counterclockwise_and_around_their_site() {
+        for (seed, spread) in [(2, RADIUS * 0.9), (3, RADIUS * 2.)] {
+            let (points, cells) = cells_of(seed, spread);
+            for cell in cells.iter() {
+                assert!(cell.area() > 0.);
+                let polygon = &cell.polygon;
+                for i in 0..polygon.len() {
+                    let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                    let c = polygon[(i + 2) % polygon.len()];
+                    assert!((b - a).perp_dot(c - b) >= -1e-2);
+                }
+                let site = points[cell.site];
+                if site.length() < RADIUS {
+                    assert!(crate::render::contains(polygon, site));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn corners_are_closest_to_their_site() {
+        let (points, cells) = cells_of(4, RADIUS * 1.5);
+        for cell in cells.iter() {
+            for corner in cell.polygon.iter() {
+                let own = corner.distance(points[cell.site]);
+                let closest = points
+                    .iter()
+                    .map(|point| corner.distance(*point))
+                    .fold(f32::INFINITY, f32::min);
+                assert!(own <= closest + 1e-2 * own.max(1.), "{} > {}", own, closest);
+                assert!(corner.length() <= RADIUS * 1.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn circumcenter_is_equidistant() {
+        let (a, b, c) = (vec2(0., 0.), vec2(4., 0.), vec2(1., 3.));
+        let center = circumcenter(a, b, c);
+        assert!((center.distance(a) - center.distance(b)).abs() < 1e-4);
+        assert!((center.distance(a) - center.distance(c)).abs() < 1e-4);
+    }
+}